@@ -0,0 +1,236 @@
+//static checks over representation, following the execution order (fi/if blocks and pin/goto)
+
+use std::fmt::{self, Display};
+
+use bitflags::bitflags;
+
+use crate::{
+    cfg::Cfg,
+    diagnostic::{DiagnosticSource, ErrorCode},
+    representation::*,
+    utility::LinedError,
+};
+
+#[derive(Debug)]
+pub enum AnalysisWarning {
+    MayLeak(String, NameType),
+    MayBeUndefined(String, NameType),
+}
+
+impl Display for AnalysisWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnalysisWarning::MayLeak(name, kind) => {
+                write!(f, "{:?} \"{}\" may still be defined at the end of the program", kind, name)
+            }
+            AnalysisWarning::MayBeUndefined(name, kind) => write!(f, "{:?} \"{}\" may be undefined at this point", kind, name),
+        }
+    }
+}
+
+impl DiagnosticSource for AnalysisWarning {
+    fn code(&self) -> ErrorCode {
+        return match self {
+            AnalysisWarning::MayLeak(_, _) => ErrorCode::MayLeak,
+            AnalysisWarning::MayBeUndefined(_, _) => ErrorCode::MayBeUndefined,
+        };
+    }
+    fn notes(&self) -> Vec<String> {
+        return match self {
+            AnalysisWarning::MayLeak(_, kind) => {
+                let undefine = match kind {
+                    NameType::Variable => "define",
+                    NameType::Array => "define single",
+                    NameType::Flag => "mark",
+                };
+                vec![format!("everything has to be undefined at the end using \"{}\"", undefine)]
+            }
+            AnalysisWarning::MayBeUndefined(_, _) => vec![],
+        };
+    }
+    fn related_name(&self) -> Option<&str> {
+        return match self {
+            AnalysisWarning::MayLeak(name, _) => Some(name),
+            AnalysisWarning::MayBeUndefined(name, _) => Some(name),
+        };
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Liveness: u8{
+        const Defined= 1<<0;
+        const Undefined=1<<1;
+    }
+}
+
+//what could be true at given point, on any of the paths reaching it
+#[derive(Debug, Clone, PartialEq)]
+struct State {
+    variables: Vec<Liveness>,
+    arrays: Vec<Liveness>,
+    flags: Vec<Liveness>,
+}
+
+impl State {
+    fn new(repr: &Representation) -> State {
+        return State {
+            variables: vec![Liveness::Undefined; repr.variables_names.len()],
+            arrays: vec![Liveness::Undefined; repr.array_names.len()],
+            flags: vec![Liveness::Defined; repr.flags_names.len()],
+        };
+    }
+
+    fn join(&self, other: &State) -> State {
+        let join = |a: &[Liveness], b: &[Liveness]| a.iter().zip(b.iter()).map(|e| *e.0 | *e.1).collect();
+        return State {
+            variables: join(&self.variables, &other.variables),
+            arrays: join(&self.arrays, &other.arrays),
+            flags: join(&self.flags, &other.flags),
+        };
+    }
+
+    fn liveness(&self, kind: &NameType, id: usize) -> Liveness {
+        return match kind {
+            NameType::Variable => self.variables[id],
+            NameType::Array => self.arrays[id],
+            NameType::Flag => self.flags[id],
+        };
+    }
+}
+
+fn avalue_names(a: AValue) -> Vec<(NameType, usize)> {
+    return match a {
+        AValue::LValue(_) => vec![],
+        AValue::RValue(r) => vec![(NameType::Variable, r.0)],
+        AValue::ArrayElement(el) => {
+            let mut names = vec![(NameType::Array, el.array_ref.0)];
+            if let IValue::RValue(r) = el.index {
+                names.push((NameType::Variable, r.0));
+            }
+            names
+        }
+    };
+}
+
+//names that have to be defined for op to make sense
+fn used_names(op: &Op) -> Vec<(NameType, usize)> {
+    return match op {
+        Op::Read(v) | Op::ReadASCII(v) => avalue_names(AValue::from(*v)),
+        Op::Print(a) | Op::PrintASCII(a) => avalue_names(*a),
+        Op::Add(a, b) | Op::Subtract(a, b) => [avalue_names(*a), avalue_names(AValue::from(*b))].concat(),
+        Op::If(a, b, _) => [avalue_names(*a), avalue_names(*b)].concat(),
+        Op::Pin(flag) => vec![(NameType::Flag, flag.0)],
+        _ => vec![],
+    };
+}
+
+fn apply(state: &mut State, op: &Op) {
+    match op {
+        Op::Define(id) => state.variables[id.0] = Liveness::Defined,
+        Op::Undefine(id) => state.variables[id.0] = Liveness::Undefined,
+        Op::DefineArray(id) => state.arrays[id.0] = Liveness::Defined,
+        Op::UndefineArray(id) => state.arrays[id.0] = Liveness::Undefined,
+        Op::Unmark(flag) => state.flags[flag.0] = Liveness::Undefined,
+        _ => {}
+    }
+}
+
+//state before every op, last one is the end of program. None when it's unreachable
+fn compute_states(repr: &Representation, cfg: &Cfg) -> Vec<Option<State>> {
+    let mut entries: Vec<Option<State>> = vec![None; cfg.blocks.len()];
+    entries[0] = Some(State::new(repr));
+    let mut work = vec![0];
+    while let Some(id) = work.pop() {
+        let mut state = entries[id].clone().unwrap();
+        for i in cfg.blocks[id].ops.clone() {
+            apply(&mut state, &repr.ops[i].op);
+        }
+        for next in cfg.blocks[id].terminator.successors() {
+            let merged = match &entries[next] {
+                Some(old) => old.join(&state),
+                None => state.clone(),
+            };
+            if entries[next].as_ref() != Some(&merged) {
+                entries[next] = Some(merged);
+                work.push(next);
+            }
+        }
+    }
+
+    let mut states: Vec<Option<State>> = vec![None; repr.ops.len() + 1];
+    for (block, entry) in cfg.blocks.iter().zip(entries) {
+        let Some(mut state) = entry else {
+            continue;
+        };
+        for i in block.ops.clone() {
+            states[i] = Some(state.clone());
+            apply(&mut state, &repr.ops[i].op);
+        }
+        if block.ops.start == repr.ops.len() {
+            states[repr.ops.len()] = Some(state);
+        }
+    }
+    return states;
+}
+
+fn get_name(repr: &Representation, kind: &NameType, id: usize) -> String {
+    return match kind {
+        NameType::Variable => repr.variables_names[id].clone(),
+        NameType::Array => repr.array_names[id].0.clone(),
+        NameType::Flag => repr.flags_names[id].clone(),
+    };
+}
+
+//leak is reported where the name is created
+fn is_creation(op: &Op, kind: &NameType, id: usize) -> bool {
+    return match (op, kind) {
+        (Op::Define(v), NameType::Variable) => v.0 == id,
+        (Op::DefineArray(a), NameType::Array) => a.0 == id,
+        (Op::Mark(f), NameType::Flag) => f.0 == id,
+        _ => false,
+    };
+}
+
+///possible leaks and uses of undefined names, on any path of execution. Empty if the if/fi blocks don't match
+pub fn check_leaks(repr: &Representation) -> Vec<LinedError<AnalysisWarning>> {
+    let Ok(cfg) = Cfg::build(repr) else {
+        return vec![];
+    };
+    let states = compute_states(repr, &cfg);
+    let warning = |op_line: &OpLine, content: AnalysisWarning| {
+        LinedError::new(op_line.line_num + 1, repr.lines_amount, op_line.line_text.clone(), content)
+    };
+    let mut warnings = Vec::new();
+
+    for (op_line, state) in repr.ops.iter().zip(states.iter()) {
+        let Some(state) = state else {
+            continue;
+        };
+        let used = used_names(&op_line.op);
+        for (i, &(kind, id)) in used.iter().enumerate() {
+            if !used[..i].contains(&(kind, id)) && state.liveness(&kind, id).contains(Liveness::Undefined) {
+                let name = get_name(repr, &kind, id);
+                warnings.push(warning(op_line, AnalysisWarning::MayBeUndefined(name, kind)));
+            }
+        }
+    }
+
+    if let Some(end) = &states[repr.ops.len()] {
+        let kinds = [
+            (NameType::Variable, repr.variables_names.len()),
+            (NameType::Array, repr.array_names.len()),
+            (NameType::Flag, repr.flags_names.len()),
+        ];
+        for (kind, amount) in kinds {
+            for id in (0..amount).filter(|&id| end.liveness(&kind, id).contains(Liveness::Defined)) {
+                let Some(op_line) = repr.ops.iter().find(|e| is_creation(&e.op, &kind, id)) else {
+                    continue;
+                };
+                let name = get_name(repr, &kind, id);
+                warnings.push(warning(op_line, AnalysisWarning::MayLeak(name, kind)));
+            }
+        }
+    }
+    return warnings;
+}
@@ -0,0 +1,187 @@
+//control flow graph of representation, shared by compiler, analysis and optimizer
+//ops are split into basic blocks, which are entered only at their first op and left only after their last one
+
+use std::ops::Range;
+
+use crate::{
+    compiler::{self, CompilerError},
+    representation::*,
+    utility::LinedError,
+};
+
+pub type BlockId = usize;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    ///falls to the next block
+    Jump(BlockId),
+    ///"fi" ending the block, condition of its "if" (op at if_op) decides
+    Branch {
+        if_op: usize,
+        then: BlockId,
+        otherwise: BlockId,
+    },
+    ///"halt", to the mark of every flag that could be pinned there, may_abort when it's possible nothing is pinned
+    Goto { targets: Vec<BlockId>, may_abort: bool },
+    ///end of the program
+    Exit,
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        return match self {
+            Terminator::Jump(next) => vec![*next],
+            Terminator::Branch { then, otherwise, .. } => vec![*then, *otherwise],
+            Terminator::Goto { targets, .. } => targets.clone(),
+            Terminator::Exit => vec![],
+        };
+    }
+}
+
+#[derive(Debug, Clone, derive_new::new)]
+pub struct BasicBlock {
+    ///indexes in Representation::ops
+    pub ops: Range<usize>,
+    pub terminator: Terminator,
+    #[new(default)]
+    pub predecessors: Vec<BlockId>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cfg {
+    ///in order of ops, the last one is empty exit block
+    pub blocks: Vec<BasicBlock>,
+    ///for every fi index of its if
+    pub matching: Vec<Option<usize>>,
+    ///index of op marking every flag
+    pub marks: Vec<usize>,
+    ///for every op block containing it, for ops.len() the exit block
+    pub block_of: Vec<BlockId>,
+}
+
+//what could be pinned at the start of a block, on any of the paths reaching it
+#[derive(Debug, Clone, PartialEq)]
+struct PinState {
+    pinned: Vec<bool>,
+    maybe_unpinned: bool,
+}
+
+impl PinState {
+    fn join(&self, other: &PinState) -> PinState {
+        return PinState {
+            pinned: self.pinned.iter().zip(other.pinned.iter()).map(|e| *e.0 || *e.1).collect(),
+            maybe_unpinned: self.maybe_unpinned || other.maybe_unpinned,
+        };
+    }
+}
+
+impl Cfg {
+    pub fn exit(&self) -> BlockId {
+        return self.blocks.len() - 1;
+    }
+
+    ///fails when fi/if blocks don't match
+    pub fn build(repr: &Representation) -> Result<Cfg, Vec<LinedError<CompilerError>>> {
+        let matching = compiler::match_blocks(repr)?;
+        let ops_amount = repr.ops.len();
+
+        let mut marks = vec![0; repr.flags_names.len()];
+        let mut leaders = vec![false; ops_amount + 1];
+        leaders[0] = true;
+        leaders[ops_amount] = true;
+        for (i, op_line) in repr.ops.iter().enumerate() {
+            match op_line.op {
+                Op::Fi => {
+                    leaders[i + 1] = true;
+                    leaders[matching[i].unwrap() + 1] = true;
+                }
+                Op::Goto => leaders[i + 1] = true,
+                Op::Mark(flag) => {
+                    marks[flag.0] = i;
+                    leaders[i] = true;
+                }
+                _ => {}
+            }
+        }
+
+        let mut block_of = Vec::with_capacity(ops_amount + 1);
+        let mut starts = Vec::new();
+        for (i, &leader) in leaders.iter().enumerate() {
+            if leader {
+                starts.push(i);
+            }
+            block_of.push(starts.len() - 1);
+        }
+
+        let mut blocks: Vec<BasicBlock> = Vec::new();
+        for (id, &start) in starts.iter().enumerate() {
+            let end = starts.get(id + 1).copied().unwrap_or(ops_amount);
+            let terminator = match repr.ops.get(end.wrapping_sub(1)).map(|e| &e.op) {
+                _ if start == ops_amount => Terminator::Exit,
+                Some(Op::Fi) => Terminator::Branch {
+                    if_op: matching[end - 1].unwrap(),
+                    then: block_of[end],
+                    otherwise: block_of[matching[end - 1].unwrap() + 1],
+                },
+                Some(Op::Goto) => Terminator::Goto {
+                    targets: vec![],
+                    may_abort: true,
+                },
+                _ => Terminator::Jump(block_of[end]),
+            };
+            blocks.push(BasicBlock::new(start..end, terminator));
+        }
+
+        let mut cfg = Cfg {
+            blocks,
+            matching,
+            marks,
+            block_of,
+        };
+        cfg.resolve_gotos(repr);
+        for id in 0..cfg.blocks.len() {
+            for next in cfg.blocks[id].terminator.successors() {
+                cfg.blocks[next].predecessors.push(id);
+            }
+        }
+        return Ok(cfg);
+    }
+
+    //label pinned last decides where goto jumps, so pins are followed to the fixpoint, adding goto edges on the way
+    fn resolve_gotos(&mut self, repr: &Representation) {
+        let mut entries: Vec<Option<PinState>> = vec![None; self.blocks.len()];
+        entries[0] = Some(PinState {
+            pinned: vec![false; repr.flags_names.len()],
+            maybe_unpinned: true,
+        });
+        let mut work = vec![0];
+        while let Some(id) = work.pop() {
+            let mut state = entries[id].clone().unwrap();
+            for i in self.blocks[id].ops.clone() {
+                if let Op::Pin(flag) = repr.ops[i].op {
+                    state.pinned.fill(false);
+                    state.pinned[flag.0] = true;
+                    state.maybe_unpinned = false;
+                }
+            }
+            if let Terminator::Goto { targets, may_abort } = &mut self.blocks[id].terminator {
+                //without pin program aborts, so there is no edge for it
+                *targets = (0..self.marks.len())
+                    .filter(|&flag| state.pinned[flag])
+                    .map(|flag| self.block_of[self.marks[flag]])
+                    .collect();
+                *may_abort = state.maybe_unpinned;
+            }
+            for next in self.blocks[id].terminator.successors() {
+                let merged = match &entries[next] {
+                    Some(old) => old.join(&state),
+                    None => state.clone(),
+                };
+                if entries[next].as_ref() != Some(&merged) {
+                    entries[next] = Some(merged);
+                    work.push(next);
+                }
+            }
+        }
+    }
+}
@@ -1,17 +1,58 @@
-use crate::{representation::*, string_builder, utility::LinedError};
-#[derive(Debug, derive_more::Display)]
+use std::fmt::{self, Display};
+
+use crate::{
+    cfg::Cfg,
+    diagnostic::{DiagnosticSource, ErrorCode},
+    exit_code::ExitCode,
+    integer::{Integers, Overflow},
+    optimizer::{self, Folding, Pending},
+    options::{BadInput, OptionFlags, Options},
+    representation::*,
+    string_builder,
+    utility::LinedError,
+};
+#[derive(Debug)]
 pub enum CompilerError {
     UmmatchedFi,
     UnmathedIf,
 }
 
+impl Display for CompilerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompilerError::UmmatchedFi => write!(f, "\"fi\" without matching \"if\""),
+            CompilerError::UnmathedIf => write!(f, "\"if\" without matching \"fi\""),
+        }
+    }
+}
+
+impl DiagnosticSource for CompilerError {
+    fn code(&self) -> ErrorCode {
+        return match self {
+            CompilerError::UmmatchedFi => ErrorCode::UnmatchedFi,
+            CompilerError::UnmathedIf => ErrorCode::UnmatchedIf,
+        };
+    }
+    fn notes(&self) -> Vec<String> {
+        let note = match self {
+            CompilerError::UmmatchedFi => "\"fi\" starts the block, so its \"if\" has to be above it",
+            CompilerError::UnmathedIf => "\"if\" ends the block, so its \"fi\" has to be below it",
+        };
+        return vec![note.to_owned()];
+    }
+}
+
 fn get_includes() -> &'static str {
-    return "#include <stdio.h>\n #include <stdbool.h>\n #include <stdlib.h>";
+    return "#include <stdio.h>\n #include <stdbool.h>\n #include <stdlib.h>\n #include <stdint.h>";
 }
 
 fn gen_variable_declaration(decl: &[String]) -> String {
     return string_builder::reduce_additive(decl.iter(), |a| {
-        format!("int {}=0; bool {}=false;\n", var_to_pvar(a), pvar_to_switch(&var_to_pvar(a)))
+        format!(
+            "value {}=0; bool {}=false;\n",
+            var_to_pvar(a),
+            pvar_to_switch(&var_to_pvar(a))
+        )
     });
 }
 fn get_array_declaration(decl: &[(String, usize)]) -> String {
@@ -21,7 +62,7 @@ fn get_array_declaration(decl: &[(String, usize)]) -> String {
         let size = var.1;
         t.push(
             format!(
-                "int {}[{}]={{}}; bool {}=false;\n",
+                "value {}[{}]={{0}}; bool {}=false;\n",
                 arr_name_to_pvar(name),
                 size,
                 pvar_to_switch(&arr_name_to_pvar(name))
@@ -32,143 +73,477 @@ fn get_array_declaration(decl: &[(String, usize)]) -> String {
     return t.collapse();
 }
 
+fn get_exit_codes() -> String {
+    let codes = [
+        ("EXIT_LEAK", ExitCode::Leak),
+        ("EXIT_USED_UNDEFINED", ExitCode::UsedUndefined),
+        ("EXIT_NOTHING_TO_PIN", ExitCode::NothingToPin),
+        ("EXIT_OUT_OF_BOUNDS", ExitCode::OutOfBounds),
+        ("EXIT_OVERFLOW", ExitCode::Overflow),
+        ("EXIT_BAD_INPUT", ExitCode::BadInput),
+    ];
+    return string_builder::reduce_additive(codes.iter(), |(name, code)| format!("#define {name} {}\n", code.code()));
+}
+
 fn get_pre_decl() -> &'static str {
-    return r#"int global=0;
-void* label=NULL;
-int err(char* text)
+    return r#"void abort_at(int code,int line,int line_from_bottom,char* line_text)
 {
-    printf("ABORTED\n:%s",text);
-    return 1;
+    printf("\nat line %d (from bottom:%d): %s",line,line_from_bottom,line_text);
+    exit(code);
 }
-#define GOTO if (label!=NULL) goto *label; else return err("nothing to pin")
-#define LEAK_CHECK(on_name,normal) if(on_name) {printf("ABORTED\nMemory leaked: %s. Everything should be undefined at the end using \"define\"",normal);return 1;} 
-#define ASSERT_ALIVE(bool_name) if(!bool_name) {printf("ABORTED\nTried to use already undefined variable/flag/array");return 1;}
-int get(int val)
+#define FAIL(code,message,line,line_from_bottom,line_text) {printf("ABORTED\n%s",message);abort_at(code,line,line_from_bottom,line_text);}
+#define LEAK_CHECK(on_name,normal) if(on_name) {printf("ABORTED\nMemory leaked: %s. Everything should be undefined at the end using \"define\"",normal);return EXIT_LEAK;} 
+#define ASSERT_ALIVE(bool_name,message,line,line_from_bottom,line_text) if(!bool_name) FAIL(EXIT_USED_UNDEFINED,message,line,line_from_bottom,line_text)
+long long check_bounds(long long index,int size,char* name,int line,int line_from_bottom,char* line_text)
 {
-    return val + global;
+    if(index<0||index>=size)
+    {
+        printf("ABORTED\nIndex %lld is out of bounds of array \"%s\"",index,name);
+        abort_at(EXIT_OUT_OF_BOUNDS,line,line_from_bottom,line_text);
+    }
+    return index;
 }
 
 "#;
 }
 
+//values are unsigned, so that overflow wraps around instead of being undefined behaviour
+//global and stored values always wrap, with --overflow trap only values seen by the program are checked
+fn get_value_decl(integers: Integers) -> String {
+    let decl = format!(
+        r#"typedef uint{bits}_t value;
+#define VALUE_BITS {bits}
+#define VALUE_MAX {max}LL
+value global=0;
+//value as seen by the program, without implementation defined conversion to signed type
+long long as_signed(value val)
+{{
+    return val>VALUE_MAX?-(long long)(value)~val-1:(long long)val;
+}}
+value get(value val)
+{{
+    return val+global;
+}}
+"#,
+        bits = integers.width.bits(),
+        max = integers.width.max()
+    );
+    if integers.overflow == Overflow::Wrap {
+        return decl
+            + r#"value to_value(long long val)
+{
+    return val;
+}
+"#;
+    }
+    return decl
+        + format!(
+            r#"#define SIGN(val) (((value)(val)>>(VALUE_BITS-1))&1)
+#define OVERFLOW(line,line_from_bottom,line_text) FAIL(EXIT_OVERFLOW,"Integer overflow, result doesn't fit in {bits}-bit integer",line,line_from_bottom,line_text)
+//value changed by temp, unless only one of a and b is at addr; "add a to a" changes it the other way
+void check_change(value* addr,value temp,bool plus,value* b,value* a,int line,int line_from_bottom,char* line_text)
+{{
+    if((addr==b)!=(addr==a)) return;
+    if(addr==b) plus=!plus;
+    value result=*addr+global;
+    value old=plus?result-temp:result+temp;
+    bool overflow=plus?SIGN(old)==SIGN(temp):SIGN(old)!=SIGN(temp);
+    if(overflow&&SIGN(result)!=SIGN(old)) OVERFLOW(line,line_from_bottom,line_text)
+}}
+value to_value(long long val,int line,int line_from_bottom,char* line_text)
+{{
+    if(val<-VALUE_MAX-1||val>VALUE_MAX) OVERFLOW(line,line_from_bottom,line_text)
+    return val;
+}}
+"#,
+            bits = integers.width.bits()
+        )
+        .as_str();
+}
+
+//"print" and "print as number", what happens on bad input is decided by --on-bad-input
+fn get_input_decl(options: &Options) -> String {
+    let fail =
+        |expected: &str| format!("FAIL(EXIT_BAD_INPUT,\"No {expected} to read on the input\",line,line_from_bottom,line_text)");
+    //bad token is skipped with the rest of the line, so the next read doesn't fail on it again
+    let skip_line = "scanf(\"%*[^\\n]\");";
+    let (on_bad_number, on_end) = match options.on_bad_input {
+        BadInput::Abort => (fail("number"), fail("byte")),
+        BadInput::Zero => (format!("{skip_line}return 0;"), "return 0;".to_owned()),
+        BadInput::Retry => (
+            format!("if(result==EOF) {}\n        {skip_line}", fail("number")),
+            fail("byte"),
+        ),
+    };
+    return format!(
+        r#"long long read_number(int line,int line_from_bottom,char* line_text)
+{{
+    long long input;
+    int result;
+    while((result=scanf("%lld",&input))!=1)
+    {{
+        {on_bad_number}
+    }}
+    return input;
+}}
+long long read_byte(int line,int line_from_bottom,char* line_text)
+{{
+    int input=getchar();
+    if(input==EOF) {on_end}
+    return input;
+}}
+"#
+    );
+}
+
+//for "read as number to" with --utf8-output
+fn get_utf8_decl(options: &Options) -> &'static str {
+    if !options.flags.contains(OptionFlags::Utf8Output) {
+        return "";
+    }
+    return r#"//invalid code points are written as U+FFFD
+void put_utf8(long long code)
+{
+    if(code<0||code>0x10FFFF||(code>=0xD800&&code<=0xDFFF)) code=0xFFFD;
+    if(code<0x80) putchar(code);
+    else if(code<0x800) {putchar(0xC0|code>>6);putchar(0x80|(code&0x3F));}
+    else if(code<0x10000) {putchar(0xE0|code>>12);putchar(0x80|(code>>6&0x3F));putchar(0x80|(code&0x3F));}
+    else {putchar(0xF0|code>>18);putchar(0x80|(code>>12&0x3F));putchar(0x80|(code>>6&0x3F));putchar(0x80|(code&0x3F));}
+}
+"#;
+}
+
+//computed goto is gnu extension, portable version dispatches on id of the mark instead
+fn get_label_decl(repr: &Representation, options: &Options) -> String {
+    if !options.flags.contains(OptionFlags::Portable) {
+        return format!("void* label=NULL;\n#define GOTO(line,line_from_bottom,line_text) if (label!=NULL) goto *label; else {NOTHING_TO_PIN}\n");
+    }
+    let cases = string_builder::reduce_additive(repr.flags_names.iter().enumerate(), |(id, name)| {
+        format!("case {id}:goto {label};", label = flag_to_label(name))
+    });
+    return format!(
+        "int label=-1;\n#define GOTO(line,line_from_bottom,line_text) switch(label){{{cases}default:{NOTHING_TO_PIN}}}\n"
+    );
+}
+
+//every defined variable and array element for check_change, only with --overflow trap
+fn get_check_values_decl(repr: &Representation) -> String {
+    if repr.integers.overflow == Overflow::Wrap {
+        return String::new();
+    }
+    let check = |addr: &str| format!("check_change(&{addr},temp,plus,b,a,line,line_from_bottom,line_text);");
+    let mut t = String::new();
+    for var in repr.variables_names.iter() {
+        let pvar = var_to_pvar(var);
+        t += format!("if({}) {}", pvar_to_switch(&pvar), check(&pvar)).as_str();
+    }
+    for (var, size) in repr.array_names.iter() {
+        let pvar = arr_name_to_pvar(var);
+        t += format!(
+            "if({}) for(int i=0;i<{size};i++) {}",
+            pvar_to_switch(&pvar),
+            check(&format!("{pvar}[i]"))
+        )
+        .as_str();
+    }
+    return format!("#define CHECK_VALUES(temp,plus,b,a,line,line_from_bottom,line_text) {{{t}}}");
+}
+
 fn get_fake_leak_check(repr: &Representation) -> String {
     let mut builder = Vec::new();
     for var in repr.variables_names.iter() {
         let pvar = var_to_pvar(var);
         let on_v = pvar_to_switch(&pvar);
-        string_builder::push(&mut builder, format!("LEAK_CHECK({on_v},\"{var}\");").as_str());
+        string_builder::push(&mut builder, format!("LEAK_CHECK({on_v},{});", to_c_string(var)).as_str());
     }
     for var in repr.array_names.iter() {
         let var = &var.0;
-        let pvar = arr_name_to_pvar(&var);
+        let pvar = arr_name_to_pvar(var);
         let on_v = pvar_to_switch(&pvar);
-        string_builder::push(&mut builder, format!("LEAK_CHECK({on_v},\"{var}\");").as_str());
+        string_builder::push(&mut builder, format!("LEAK_CHECK({on_v},{});", to_c_string(var)).as_str());
     }
     for var in repr.flags_names.iter() {
         let pvar = flag_to_pvar(var);
         let on_v = pvar_to_switch(&pvar);
-        string_builder::push(&mut builder, format!("LEAK_CHECK({on_v},\"{var}\");").as_str());
+        string_builder::push(&mut builder, format!("LEAK_CHECK({on_v},{});", to_c_string(var)).as_str());
     }
     return string_builder::collapse(builder);
 }
 
-fn aval_as_txt(avalue: AValue, repr: &Representation) -> String {
+//everything needed to compile single op
+#[derive(derive_new::new)]
+struct Context<'a> {
+    repr: &'a Representation,
+    options: &'a Options,
+    op: &'a OpLine,
+    ///offsets folded by optimizer, empty without it
+    pending: &'a Pending,
+}
+
+fn aval_as_txt(avalue: AValue, ctx: &Context) -> String {
     return match avalue {
-        AValue::LValue(l) => l.to_string(),
-        AValue::RValue(r) => "__".to_owned() + repr.variables_names[r.0].clone().as_str(),
-        AValue::ArrayElement(el) => format!(
-            "__{}[{}]",
-            repr.get_array_name(el.array_ref).as_str(),
-            aval_as_txt(el.index.as_avalue(), repr)
-        ),
+        AValue::LValue(l) => int_literal(l),
+        AValue::RValue(_) | AValue::ArrayElement(_) => get_pvar_from_repr(VValue::try_from(avalue).unwrap(), ctx),
     };
 }
 
-fn compile_math_line(a: AValue, b: VValue, plus: bool, repr: &Representation) -> Option<String> {
-    let plus_op = if plus { "+" } else { "-" };
+fn compile_math_line(a: AValue, b: VValue, plus: bool, ctx: &Context) -> Option<String> {
     let minus_op = if plus { "-" } else { "+" };
+    let optimize = ctx.options.flags.contains(OptionFlags::Optimize);
+
+    if is_folding(ctx.repr, ctx.options) && optimizer::is_folded(&ctx.op.op) {
+        //global and variable are changed by the pending offsets, only array element is left
+        let assert_b = get_alive_assert(AValue::from(b), ctx);
+        if let VValue::ArrayElement(_) = b {
+            return Some(format!(
+                "{assert_b}{nb}{minus_op}={v};",
+                nb = get_pvar_from_repr(b, ctx),
+                v = aval_as_txt(a, ctx)
+            ));
+        }
+        return Some(assert_b);
+    }
+    if let (true, VValue::RValue(_)) = (optimize, b) {
+        //variable's address doesn't depend on global
+        let mut t = format!(
+            "{{{assert_a}{assert_b}value temp={v};{global}{b}",
+            global = update("global", plus, "temp"),
+            b = update(&get_pvar_from_repr(b, ctx), !plus, "temp"),
+            v = aval_as_get(a, ctx),
+            assert_a = get_alive_assert(a, ctx),
+            assert_b = get_alive_assert(AValue::from(b), ctx)
+        );
+        if let Ok(a) = VValue::try_from(a) {
+            t += update(&get_pvar_from_repr(a, ctx), !plus, "temp").as_str();
+        }
+        t += check_values(plus, &format!("&{}", get_pvar_from_repr(b, ctx)), a, ctx).as_str();
+        t += "}";
+        return Some(t);
+    }
 
     // addr and temp is cached before so it is not affected by global change
     // addr could be removed if we cached non literal indexes. *probably* gcc compiles it away with optimization enabled
     // counterintuitive element of gnalose: index used to access element is not sheltered from the effect add/sub
 
     let mut t = format!(
-        "{{{assert_a}{assert_b}int temp={v};int* addr=&{nb};global{plus_op}=temp;(*addr){minus_op}=temp;",
-        nb = get_pvar_from_repr(b, repr),
-        v = aval_as_get(a, repr),
-        assert_a = get_alive_assert(a, repr),
-        assert_b = get_alive_assert(AValue::from(b), repr)
+        "{{{assert_a}{assert_b}value temp={v};value* addr=&{nb};{global}{b}",
+        nb = get_pvar_from_repr(b, ctx),
+        global = update("global", plus, "temp"),
+        b = update("(*addr)", !plus, "temp"),
+        v = aval_as_get(a, ctx),
+        assert_a = get_alive_assert(a, ctx),
+        assert_b = get_alive_assert(AValue::from(b), ctx)
     );
     if let AValue::RValue(a_id) = a {
         //also safe "a" from effect if it's not literal
-        t += format!("{na}{minus_op}=temp;", na = get_pvar_from_repr(VValue::RValue(a_id), repr)).as_str();
+        t += update(&get_pvar_from_repr(VValue::RValue(a_id), ctx), !plus, "temp").as_str();
     }
     if let AValue::ArrayElement(element) = a {
-        t += format!(
-            "{na}{minus_op}=temp;",
-            na = get_pvar_from_repr(VValue::ArrayElement(element), repr)
-        )
-        .as_str();
+        t += update(&get_pvar_from_repr(VValue::ArrayElement(element), ctx), !plus, "temp").as_str();
     }
+    t += check_values(plus, "addr", a, ctx).as_str();
     t += "}";
     return Some(t);
 }
 
+const NOTHING_TO_PIN: &str =
+    r#"FAIL(EXIT_NOTHING_TO_PIN,"Nothing to pin, \"halt\" used before any \"forget\"",line,line_from_bottom,line_text)"#;
+
+//names can contain any character, so everything besides ascii letters and digits is written as its hex code between "_"
+//that keeps different names different, and prefix keeps them away from c keywords and symbols from get_pre_decl
+fn mangle(prefix: &str, t: &str) -> String {
+    let mut result = prefix.to_owned();
+    for c in t.chars() {
+        if c.is_ascii_alphanumeric() {
+            result.push(c);
+        } else {
+            result += format!("_{:x}_", c as u32).as_str();
+        }
+    }
+    return result;
+}
 fn arr_name_to_pvar(t: &str) -> String {
-    return format!("_a_{}", t);
+    return mangle("a_", t);
 }
 fn flag_to_pvar(t: &str) -> String {
-    return format!("_f_{}", t);
+    return mangle("f_", t);
+}
+fn flag_to_label(t: &str) -> String {
+    return mangle("l_", t);
 }
 fn var_to_pvar(t: &str) -> String {
-    return format!("__{}", t);
+    return mangle("v_", t);
 }
 fn pvar_to_switch(t: &str) -> String {
-    return format!("_isOn{}", t);
+    return format!("on_{}", t);
+}
+//for original names and lines in runtime messages, "?" because of trigraphs
+fn to_c_string(t: &str) -> String {
+    let mut result = String::from("\"");
+    for chr in t.chars() {
+        match chr {
+            '\\' | '"' | '?' => result.extend(['\\', chr]),
+            '\n' => result += "\\n",
+            //octal escape has at most 3 digits, so it can't eat the next char
+            _ if chr.is_ascii_control() => result += format!("\\{:03o}", chr as u32).as_str(),
+            _ => result.push(chr),
+        }
+    }
+    result.push('"');
+    return result;
+}
+//line, line from bottom and text of the line, the way abort_at takes them
+fn get_location(ctx: &Context) -> String {
+    return format!(
+        "{},{},{}",
+        ctx.repr.get_line_from_top(ctx.op),
+        ctx.op.line_num + 1,
+        to_c_string(ctx.op.line_text.trim())
+    );
+}
+
+fn get_array_index(el: ArrayElement, ctx: &Context) -> String {
+    let index = aval_as_signed(AValue::from(el.index), ctx);
+    if ctx.options.flags.contains(OptionFlags::NoBoundsCheck) {
+        return index;
+    }
+    return format!(
+        "check_bounds({index},{size},{name},{location})",
+        size = ctx.repr.get_array_size(el.array_ref),
+        name = to_c_string(&ctx.repr.get_array_name(el.array_ref)),
+        location = get_location(ctx)
+    );
 }
 
-fn get_pvar_from_repr(id: VValue, repr: &Representation) -> String {
+fn get_pvar_from_repr(id: VValue, ctx: &Context) -> String {
     return match id {
         VValue::ArrayElement(el) => {
             format!(
                 "{}[{}]",
-                arr_name_to_pvar(&repr.get_array_name(el.array_ref)),
-                aval_as_get(AValue::from(el.index), repr)
+                arr_name_to_pvar(&ctx.repr.get_array_name(el.array_ref)),
+                get_array_index(el, ctx)
             )
         }
-        VValue::RValue(el) => var_to_pvar(&repr.get_variable_name(el)),
+        VValue::RValue(el) => var_to_pvar(&ctx.repr.get_variable_name(el)),
     };
 }
-fn get_switch_from_repr(id: VValue, repr: &Representation) -> String {
-    return pvar_to_switch(&get_pvar_from_repr(id, repr));
+//whole array shares one switch
+fn get_switch_from_repr(id: VValue, ctx: &Context) -> String {
+    return match id {
+        VValue::ArrayElement(el) => pvar_to_switch(&arr_name_to_pvar(&ctx.repr.get_array_name(el.array_ref))),
+        VValue::RValue(_) => pvar_to_switch(&get_pvar_from_repr(id, ctx)),
+    };
 }
 fn get_flag_swith_from_repr(id: FlagRef, repr: &Representation) -> String {
     return pvar_to_switch(&flag_to_pvar(&repr.get_flag_name(id)));
 }
-fn get_alive_assert(id: AValue, repr: &Representation) -> String {
+fn get_alive_assert(id: AValue, ctx: &Context) -> String {
     match id {
         AValue::LValue(_) => "".into(),
         AValue::ArrayElement(_) | AValue::RValue(_) => {
+            let (kind, name) = match id {
+                AValue::ArrayElement(el) => (NameType::Array, ctx.repr.get_array_name(el.array_ref)),
+                _ => (NameType::Variable, ctx.repr.get_variable_name(RValue::try_from(id).unwrap())),
+            };
             format!(
-                "ASSERT_ALIVE({on});",
-                on = get_switch_from_repr(VValue::try_from(id).unwrap(), repr)
+                "ASSERT_ALIVE({on},{message},{location});",
+                on = get_switch_from_repr(VValue::try_from(id).unwrap(), ctx),
+                message = get_undefined_message(kind, &name),
+                location = get_location(ctx)
             )
         }
     }
 }
-fn get_flag_alive_assert(id: FlagRef, repr: &Representation) -> String {
-    return format!("ASSERT_ALIVE({on});", on = get_flag_swith_from_repr(id, repr));
+fn get_flag_alive_assert(id: FlagRef, ctx: &Context) -> String {
+    return format!(
+        "ASSERT_ALIVE({on},{message},{location});",
+        on = get_flag_swith_from_repr(id, ctx.repr),
+        message = get_undefined_message(NameType::Flag, &ctx.repr.get_flag_name(id)),
+        location = get_location(ctx)
+    );
+}
+fn get_undefined_message(kind: NameType, name: &str) -> String {
+    return to_c_string(&format!("Tried to use already undefined {} \"{}\"", kind.describe(), name));
 }
 
-fn aval_as_get(a: AValue, repr: &Representation) -> String {
-    match a {
-        AValue::LValue(l) => l.to_string().to_owned(),
-        AValue::RValue(_) | AValue::ArrayElement(_) => format!("get({})", aval_as_txt(a, repr)),
+fn aval_as_get(a: AValue, ctx: &Context) -> String {
+    let offset = match a {
+        AValue::LValue(l) => return int_literal(l),
+        AValue::RValue(id) => ctx.pending.value_offset(id),
+        AValue::ArrayElement(_) => ctx.pending.global,
+    };
+    let get = format!("get({})", aval_as_txt(a, ctx));
+    if offset == 0 {
+        return get;
+    }
+    return format!("({}{})", get, signed_offset(offset));
+}
+//printed, compared and used as index as signed number
+fn aval_as_signed(a: AValue, ctx: &Context) -> String {
+    if let AValue::LValue(l) = a {
+        return int_literal(l);
+    }
+    return format!("as_signed({})", aval_as_get(a, ctx));
+}
+//long long, since it's also passed to printf. 9223372036854775808 isn't literal in c, so the smallest number can't be written directly
+fn int_literal(value: i64) -> String {
+    if value == i64::MIN {
+        return "(-9223372036854775807LL-1)".to_owned();
+    }
+    return format!("{}LL", value);
+}
+//"+n"/"-n" or nothing for 0
+fn signed_offset(offset: i64) -> String {
+    return match offset {
+        0 => String::new(),
+        i64::MIN => "-9223372036854775807LL-1".to_owned(),
+        _ => format!("{:+}", offset),
+    };
+}
+//location as the last arguments of function that can trap, nothing when overflow wraps
+fn trap_location(ctx: &Context) -> String {
+    if ctx.repr.integers.overflow == Overflow::Wrap {
+        return String::new();
+    }
+    return format!(",{}", get_location(ctx));
+}
+//"target+=operand;" or "target-=operand;"
+fn update(target: &str, plus: bool, operand: &str) -> String {
+    return format!("{target}{}={operand};", if plus { "+" } else { "-" });
+}
+//raw value of just defined variable, so that its value is 0
+fn negated_global(ctx: &Context) -> String {
+    return format!("-global{}", signed_offset(ctx.pending.global.wrapping_neg()));
+}
+//with --overflow trap every defined value is checked after add/sub, b is address taken before the change
+fn check_values(plus: bool, b: &str, a: AValue, ctx: &Context) -> String {
+    if ctx.repr.integers.overflow == Overflow::Wrap {
+        return String::new();
+    }
+    let a = match VValue::try_from(a) {
+        Ok(a) => format!("&{}", get_pvar_from_repr(a, ctx)),
+        Err(_) => "NULL".to_owned(),
+    };
+    return format!("CHECK_VALUES(temp,{plus},{b},{a},{});", get_location(ctx));
+}
+//folded offsets would hide overflow in the middle, so they are only used when it wraps
+fn is_folding(repr: &Representation, options: &Options) -> bool {
+    return options.flags.contains(OptionFlags::Optimize) && repr.integers.overflow == Overflow::Wrap;
+}
+//writes offsets folded by optimizer to the memory
+fn get_flush(pending: &Pending, repr: &Representation) -> String {
+    let mut t = String::new();
+    if pending.global != 0 {
+        t += format!("global+={};", signed_offset(pending.global)).as_str();
     }
+    for (id, offset) in pending.variables.iter() {
+        t += format!("{}-={};", var_to_pvar(&repr.get_variable_name(*id)), signed_offset(*offset)).as_str();
+    }
+    return t;
 }
-fn if_to_text(a: AValue, b: AValue, cond: ConditionType, repr: &Representation) -> String {
-    let a_name = aval_as_get(a, &repr);
-    let b_name = aval_as_get(b, &repr);
+fn if_to_text(a: AValue, b: AValue, cond: ConditionType, ctx: &Context) -> String {
+    let a_name = aval_as_signed(a, ctx);
+    let b_name = aval_as_signed(b, ctx);
     let operator = match cond {
         ConditionType::Equal => "==",
         ConditionType::NotEqual => "!=",
@@ -184,137 +559,197 @@ fn push_builder(txt: &str, builder: &mut String) {
     *builder = (builder.to_owned() + txt) + "\n";
 }
 
-#[derive(Debug, derive_new::new)]
-struct IfConstruct {
-    a: AValue,
-    b: AValue,
-    cond_type: ConditionType,
-    lines: usize,
-}
-#[derive(Debug, derive_new::new)]
-struct CodeBlock {
-    code: String,
-    last_line: usize,
-    if_ending: Option<IfConstruct>,
-}
-fn try_compile_to_trivial_line(op: &OpLine, repr: &Representation) -> Option<String> {
-    match &op.op {
+fn try_compile_to_trivial_line(ctx: &Context) -> Option<String> {
+    let repr = ctx.repr;
+    match &ctx.op.op {
         Op::Define(id) => Some(format!(
-            "{n}=-global;{on}=true;",
-            n = get_pvar_from_repr(VValue::RValue(*id), &repr),
-            on = get_switch_from_repr(VValue::RValue(*id), &repr)
+            "{n}={negated};{on}=true;",
+            n = get_pvar_from_repr(VValue::RValue(*id), ctx),
+            negated = negated_global(ctx),
+            on = get_switch_from_repr(VValue::RValue(*id), ctx)
         )),
         Op::DefineArray(id) => {
             let n = arr_name_to_pvar(repr.get_array_name(*id).as_str());
             //TODO: could have global offset for given array for better runtime performance
             return Some(format!(
-                "for(int i=0;i<{size};i++)\n{{\n{n}[i]=-global;\n}}\n{on}=true;",
+                "for(int i=0;i<{size};i++){{{n}[i]={negated};}}{on}=true;",
                 size = repr.get_array_size(*id),
+                negated = negated_global(ctx),
                 on = pvar_to_switch(n.as_str()),
             ));
         }
-        Op::Undefine(id) => Some(format!("{on}=false;", on = get_switch_from_repr(VValue::RValue(*id), &repr))),
+        Op::Undefine(id) => Some(format!("{on}=false;", on = get_switch_from_repr(VValue::RValue(*id), ctx))),
 
         Op::UndefineArray(id) => Some(format!(
             "{on}=false;",
             on = pvar_to_switch(arr_name_to_pvar(repr.get_array_name(*id).as_str()).as_str())
         )),
-        Op::Read(id) => Some(format!(
-            "{assert}scanf(\"%d\",&{n});{n}-=global;",
-            assert = get_alive_assert(AValue::from(*id), repr),
-            n = get_pvar_from_repr(*id, &repr)
+        Op::Read(id) | Op::ReadASCII(id) => Some(format!(
+            "{assert}{n}=to_value({function}({location}){trap_location});{update}",
+            assert = get_alive_assert(AValue::from(*id), ctx),
+            function = if let Op::Read(_) = ctx.op.op {
+                "read_number"
+            } else {
+                "read_byte"
+            },
+            location = get_location(ctx),
+            trap_location = trap_location(ctx),
+            update = update(
+                &get_pvar_from_repr(*id, ctx),
+                false,
+                &format!("global{}", signed_offset(ctx.pending.global))
+            ),
+            n = get_pvar_from_repr(*id, ctx)
         )),
         Op::Print(val) => Some(format!(
-            "{assert}printf(\"%d\\n\",{n});",
-            assert = get_alive_assert(*val, repr),
-            n = aval_as_get(*val, repr)
+            "{assert}printf(\"%lld\\n\",{n});",
+            assert = get_alive_assert(*val, ctx),
+            n = aval_as_signed(*val, ctx)
+        )),
+        Op::PrintASCII(val) if ctx.options.flags.contains(OptionFlags::Utf8Output) => Some(format!(
+            "{assert}put_utf8({n});",
+            assert = get_alive_assert(*val, ctx),
+            n = aval_as_signed(*val, ctx)
         )),
         Op::PrintASCII(val) => Some(format!(
-            "{assert}printf(\"%c\\n\",(char){n});",
-            assert = get_alive_assert(*val, repr),
-            n = aval_as_get(*val, repr)
+            "{assert}putchar((unsigned char){n});",
+            assert = get_alive_assert(*val, ctx),
+            n = aval_as_get(*val, ctx)
         )),
-        Op::Add(a, b) => compile_math_line(*a, *b, true, repr),
-        Op::Subtract(a, b) => compile_math_line(*a, *b, false, repr),
-        Op::Mark(flag) => Some(format!("{}:", repr.get_flag_name(*flag))),
+        Op::PrintText(text) => Some(format!("fputs({},stdout);", to_c_string(text))),
+        Op::Add(a, b) => compile_math_line(*a, *b, true, ctx),
+        Op::Subtract(a, b) => compile_math_line(*a, *b, false, ctx),
+        //empty statement, label can't end the block in standard c
+        Op::Mark(flag) => Some(format!("{}:;", flag_to_label(&repr.get_flag_name(*flag)))),
         Op::Unmark(flag) => Some(format!(
             "{on}=false;",
             on = pvar_to_switch(flag_to_pvar(repr.get_flag_name(*flag).as_str()).as_str())
         )),
+        Op::Pin(flag) if ctx.options.flags.contains(OptionFlags::Portable) => Some(format!(
+            "{assert}label={};",
+            flag.0,
+            assert = get_flag_alive_assert(*flag, ctx),
+        )),
         Op::Pin(flag) => Some(format!(
             "{assert}label=&&{};",
-            repr.get_flag_name(*flag),
-            assert = get_flag_alive_assert(*flag, repr),
+            flag_to_label(&repr.get_flag_name(*flag)),
+            assert = get_flag_alive_assert(*flag, ctx),
         )),
-        Op::Goto => Some("GOTO;".to_owned()),
+        Op::Goto => Some(format!("GOTO({});", get_location(ctx))),
         Op::If(_, _, _) => None,
         Op::Fi => None,
     }
 }
-fn compile_internal(ops: &[OpLine], repr: &Representation, line_am: usize) -> Result<CodeBlock, LinedError<CompilerError>> {
+//backslash at the end would join the comment with the next line of c
+fn line_comment(line_text: &str) -> String {
+    if line_text.trim_end().ends_with('\\') {
+        return format!("//{}//", line_text);
+    }
+    return format!("//{}", line_text);
+}
+//so c compiler and debugger point at the gnalose source, every op is compiled into single line of c
+fn push_line_directive(builder: &mut Vec<char>, ctx: &Context) {
+    if !ctx.options.flags.contains(OptionFlags::LineDirectives) {
+        return;
+    }
+    let file = ctx.options.file_name.as_deref().unwrap_or("<input>");
+    let directive = format!("#line {} {}", ctx.repr.get_line_from_top(ctx.op), to_c_string(file));
+    string_builder::push_line(builder, &directive);
+}
+//ops are emitted in execution order, "fi" opens c block with condition of its "if", which closes it
+fn compile_ops(repr: &Representation, options: &Options, cfg: &Cfg, folding: &Folding) -> String {
     let mut builder: Vec<char> = Vec::new();
-    let mut i = 0;
-    while i < ops.len() {
-        let op_line = &ops[i];
-        let trivial = try_compile_to_trivial_line(&op_line, repr).map(|r| r + format!("//{}", op_line.line_text).as_str());
-
-        if let Some(trivial_content) = trivial {
-            string_builder::push_line(&mut builder, trivial_content.as_str());
-        } else if let Op::Fi = op_line.op {
-            let block = compile_internal(&ops[(i + 1)..], repr, line_am)?;
-            if let Some(if_content) = block.if_ending {
-                let if_text = if_to_text(if_content.a, if_content.b, if_content.cond_type, repr);
-                string_builder::push_line(&mut builder, &if_text);
-                string_builder::push_line(&mut builder, &block.code);
+    for (i, op_line) in repr.ops.iter().enumerate() {
+        let ctx = Context::new(repr, options, op_line, &folding.pending[i]);
+        let flush = get_flush(&folding.flush_before[i], repr);
+
+        if let Some(trivial_content) = try_compile_to_trivial_line(&ctx) {
+            push_line_directive(&mut builder, &ctx);
+            string_builder::push_line(
+                &mut builder,
+                &format!("{flush}{trivial_content}{}", line_comment(&op_line.line_text)),
+            );
+            continue;
+        }
+        match op_line.op {
+            Op::Fi => {
+                let if_index = cfg.matching[i].unwrap();
+                let if_ctx = Context::new(repr, options, &repr.ops[if_index], &folding.pending[if_index]);
+                let Op::If(a, b, cond) = repr.ops[if_index].op else {
+                    unreachable!("fi is matched with if");
+                };
+                push_line_directive(&mut builder, &if_ctx);
+                string_builder::push_line(&mut builder, &format!("{flush}{}", if_to_text(a, b, cond, &if_ctx)));
+            }
+            _ => {
+                if !flush.is_empty() {
+                    string_builder::push_line(&mut builder, &flush);
+                }
                 string_builder::push_line(&mut builder, "}");
-                i += if_content.lines;
-            } else {
-                return Err(LinedError::new(
-                    op_line.line_num,
-                    line_am,
-                    op_line.line_text.clone(),
-                    CompilerError::UmmatchedFi,
-                ));
             }
         }
-        if let Op::If(a, b, cond) = &op_line.op {
-            let constr = IfConstruct::new(*a, *b, *cond, i + 1);
-            return Ok(CodeBlock::new(string_builder::collapse(builder), i, Some(constr)));
+    }
+    return string_builder::collapse(builder);
+}
+
+///for every fi index of its if, all unmatched fi/if are reported
+pub fn match_blocks(repr: &Representation) -> Result<Vec<Option<usize>>, Vec<LinedError<CompilerError>>> {
+    let error = |op_line: &OpLine, err: CompilerError| {
+        LinedError::new(op_line.line_num + 1, repr.lines_amount, op_line.line_text.clone(), err)
+    };
+    let mut matching = vec![None; repr.ops.len()];
+    let mut errors = Vec::new();
+    let mut open: Vec<usize> = Vec::new();
+
+    for (i, op_line) in repr.ops.iter().enumerate() {
+        match op_line.op {
+            Op::Fi => open.push(i),
+            Op::If(_, _, _) => match open.pop() {
+                Some(fi) => matching[fi] = Some(i),
+                None => errors.push(error(op_line, CompilerError::UnmathedIf)),
+            },
+            _ => {}
         }
-        i += 1;
     }
-    return Ok(CodeBlock::new(string_builder::collapse(builder), i - 1, None));
+    for fi in open {
+        errors.push(error(&repr.ops[fi], CompilerError::UmmatchedFi));
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    return Ok(matching);
 }
 
 fn get_empty_progam() -> &'static str {
     return "int main(){}";
 }
-pub fn compile(repr: &Representation) -> Result<String, LinedError<CompilerError>> {
-    if repr.ops.len() == 0 {
+pub fn compile(repr: &Representation, options: &Options) -> Result<String, Vec<LinedError<CompilerError>>> {
+    if repr.ops.is_empty() {
         return Ok(get_empty_progam().to_owned());
     }
+    let cfg = Cfg::build(repr)?;
 
-    let lines_count = repr.ops.last().unwrap().line_num;
     let mut builder = String::new();
     builder = (builder + get_includes()) + "\n";
+    builder = (builder + get_exit_codes().as_str()) + "\n";
     builder = (builder + get_pre_decl()) + "\n";
+    builder = (builder + get_value_decl(repr.integers).as_str()) + "\n";
+    builder = (builder + get_input_decl(options).as_str()) + "\n";
+    builder = (builder + get_utf8_decl(options)) + "\n";
+    builder = (builder + get_label_decl(repr, options).as_str()) + "\n";
+    builder = (builder + get_check_values_decl(repr).as_str()) + "\n";
     builder += "int main(){\n";
-    push_builder(&gen_variable_declaration(&repr.variables_names).as_str(), &mut builder);
-    push_builder(&get_array_declaration(&repr.array_names).as_str(), &mut builder);
-    push_builder(&get_flag_on_bools(&repr.flags_names).as_str(), &mut builder);
-
-    let result = &compile_internal(&repr.ops, repr, lines_count)?;
-    if !result.if_ending.is_none() {
-        let rel_op = &repr.ops[result.last_line];
-        return Err(LinedError::new(
-            rel_op.line_num,
-            lines_count,
-            rel_op.line_text.clone(),
-            CompilerError::UnmathedIf,
-        ));
-    }
-    push_builder(&result.code, &mut builder);
-    push_builder(get_fake_leak_check(&repr).as_str(), &mut builder);
+    push_builder(gen_variable_declaration(&repr.variables_names).as_str(), &mut builder);
+    push_builder(get_array_declaration(&repr.array_names).as_str(), &mut builder);
+    push_builder(get_flag_on_bools(&repr.flags_names).as_str(), &mut builder);
+
+    let folding = if is_folding(repr, options) {
+        optimizer::fold_offsets(repr, &cfg)
+    } else {
+        Folding::none(repr.ops.len())
+    };
+    push_builder(&compile_ops(repr, options, &cfg, &folding), &mut builder);
+    push_builder(get_fake_leak_check(repr).as_str(), &mut builder);
 
     builder += "}";
     return Ok(builder);
@@ -0,0 +1,109 @@
+//concrete syntax tree for tooling: every source line with its comments, indentation and op
+//unlike Representation nothing is lost, to_source gives back the original text byte for byte
+
+use crate::{lexer, representation::*, token::Token};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstLine {
+    ///whitespace before the first comment or code
+    pub indent: String,
+    ///comment segments in order, without their "/"
+    pub comments: Vec<String>,
+    ///code after the comments exactly as written, trailing whitespace included
+    pub code: String,
+    ///tokens of the code, without comments
+    pub tokens: Vec<Token>,
+    ///every token of the code as written, e.g. "'a'" for Literal(97)
+    pub token_texts: Vec<String>,
+    ///None for empty and comment only lines
+    pub op: Option<Op>,
+    ///"\n", "\r\n" or "" for the last line without it
+    pub line_ending: String,
+}
+
+impl CstLine {
+    pub fn is_blank(&self) -> bool {
+        return self.comments.is_empty() && self.tokens.is_empty();
+    }
+}
+
+#[derive(Debug)]
+pub struct Cst {
+    ///file started with byte order mark
+    pub bom: bool,
+    ///from the top, the way they are in the file
+    pub lines: Vec<CstLine>,
+    ///names the ops refer to
+    pub repr: Representation,
+}
+
+impl Cst {
+    ///code has to be the one repr was parsed from
+    pub fn build(code: &str, repr: Representation) -> Cst {
+        let bom = code.starts_with('\u{feff}');
+        let code = code.strip_prefix('\u{feff}').unwrap_or(code);
+        let mut lines = Vec::new();
+
+        //same lines as str::lines, but with their endings
+        for raw in code.split_inclusive('\n') {
+            let (content, line_ending) = match raw.strip_suffix("\r\n") {
+                Some(content) => (content, "\r\n"),
+                None => match raw.strip_suffix('\n') {
+                    Some(content) => (content, "\n"),
+                    None => (raw, ""),
+                },
+            };
+            let rest = content.trim_start();
+            let (tokens, spans) =
+                lexer::tokenize_line_spanned(content.trim(), repr.integers.width).expect("code was already tokenized");
+            let trimmed: Vec<char> = content.trim().chars().collect();
+
+            let mut comments = Vec::new();
+            let mut code_start = content.len() - rest.len();
+            let mut code_tokens = Vec::new();
+            let mut token_texts = Vec::new();
+            for (token, span) in tokens.into_iter().zip(spans) {
+                match token {
+                    Token::Comment(text) => {
+                        code_start += text.len() + 1;
+                        comments.push(text);
+                    }
+                    _ => {
+                        code_tokens.push(token);
+                        token_texts.push(trimmed[span].iter().collect());
+                    }
+                }
+            }
+            lines.push(CstLine {
+                indent: content[..content.len() - rest.len()].to_owned(),
+                comments,
+                code: content[code_start..].to_owned(),
+                tokens: code_tokens,
+                token_texts,
+                op: None,
+                line_ending: line_ending.to_owned(),
+            });
+        }
+
+        //line_num is counted from the bottom
+        for op_line in repr.ops.iter() {
+            let index = lines.len() - 1 - op_line.line_num;
+            lines[index].op = Some(op_line.op.clone());
+        }
+        return Cst { bom, lines, repr };
+    }
+
+    pub fn to_source(&self) -> String {
+        let mut source = if self.bom { "\u{feff}".to_owned() } else { String::new() };
+        for line in self.lines.iter() {
+            source += line.indent.as_str();
+            for comment in line.comments.iter() {
+                source += comment.as_str();
+                source += "/";
+            }
+            source += line.code.as_str();
+            source += line.line_ending.as_str();
+        }
+        return source;
+    }
+}
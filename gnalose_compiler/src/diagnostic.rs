@@ -0,0 +1,171 @@
+use std::{
+    error::Error,
+    fmt::{self, Debug, Display},
+};
+
+use crate::{integer::IntWidth, lexer, token::*, utility::LinedError};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    Lexer,
+    Parser,
+    Compiler,
+    Analysis,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+///stable codes, never reuse a number
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorCode {
+    InvalidLiteral = 1,
+    NameUsedTwice = 2,
+    NotDefinedVariable = 3,
+    InvalidStructure = 4,
+    DoubleLabel = 5,
+    UnmatchedFi = 6,
+    UnmatchedIf = 7,
+    MayLeak = 8,
+    MayBeUndefined = 9,
+    Unreachable = 10,
+    NeverPinned = 11,
+    NeverRead = 12,
+    NegativeArraySize = 13,
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "G{:04}", *self as u32)
+    }
+}
+
+///step specific error that can be turned into diagnostic
+pub trait DiagnosticSource: Debug + Display {
+    fn code(&self) -> ErrorCode;
+    fn notes(&self) -> Vec<String> {
+        return vec![];
+    }
+    ///name of the token the error is about
+    fn related_name(&self) -> Option<&str> {
+        return None;
+    }
+    ///by default token with related name, or the whole line when there is none
+    fn span(&self, line_text: &str) -> Option<Span> {
+        let (tokens, spans) = lexer::tokenize_line_spanned(line_text.trim(), IntWidth::W64).ok()?;
+        let indent = line_text.chars().count() - line_text.trim_start().chars().count();
+        let code_spans: Vec<(&Token, &Span)> = tokens
+            .iter()
+            .zip(spans.iter())
+            .filter(|e| !matches!(e.0, Token::Comment(_)))
+            .collect();
+
+        let span = match self.related_name() {
+            Some(name) => code_spans
+                .iter()
+                .find(|e| matches!(e.0, Token::Name(n) if n == name))
+                .map(|e| e.1.clone())?,
+            None => code_spans.first()?.1.start..code_spans.last()?.1.end,
+        };
+        return Some(span.start + indent..span.end + indent);
+    }
+}
+
+///error from any of the compilation steps, no longer depends on the step specific error type
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub stage: Stage,
+    pub severity: Severity,
+    pub code: ErrorCode,
+    pub message: String,
+    pub file: Option<String>,
+    ///counted from the bottom, starting with 1, same as in LinedError
+    pub line: usize,
+    pub lines_amount: usize,
+    pub line_text: String,
+    pub span: Option<Span>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn from_lined<T>(stage: Stage, err: LinedError<T>) -> Diagnostic
+    where
+        T: DiagnosticSource,
+    {
+        return Diagnostic {
+            stage,
+            severity: Severity::Error,
+            code: err.content.code(),
+            message: err.content.to_string(),
+            file: None,
+            line: err.line,
+            lines_amount: err.lines_amount,
+            span: err.content.span(&err.related_text),
+            line_text: err.related_text,
+            notes: err.content.notes(),
+        };
+    }
+
+    ///sorted in source order, from the top
+    pub fn from_lined_all<T>(stage: Stage, errors: Vec<LinedError<T>>) -> Vec<Diagnostic>
+    where
+        T: DiagnosticSource,
+    {
+        let mut diagnostics: Vec<Diagnostic> = errors.into_iter().map(|e| Diagnostic::from_lined(stage, e)).collect();
+        diagnostics.sort_by_key(|e| e.get_line_from_top());
+        return diagnostics;
+    }
+
+    pub fn with_severity(mut self, severity: Severity) -> Diagnostic {
+        self.severity = severity;
+        return self;
+    }
+
+    pub fn with_file(mut self, file: &str) -> Diagnostic {
+        self.file = Some(file.to_owned());
+        return self;
+    }
+
+    ///line number counted from the top, the way it's seen in the source file
+    pub fn get_line_from_top(&self) -> usize {
+        return self.lines_amount - (self.line - 1);
+    }
+}
+
+impl Error for Diagnostic {}
+impl Display for Diagnostic {
+    //rustc like, caret under the offending token
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = self.get_line_from_top();
+        let column = self.span.as_ref().map(|e| e.start + 1).unwrap_or(1);
+        let file = self.file.as_deref().unwrap_or("<input>");
+        let gutter = " ".repeat(line.to_string().len());
+
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        writeln!(f, "{severity}[{}]: {}", self.code, self.message)?;
+        writeln!(f, "{gutter}--> {file}:{line}:{column} (from bottom:{})", self.line)?;
+        writeln!(f, "{gutter} |")?;
+        write!(f, "{line} | {}", self.line_text)?;
+        if let Some(span) = &self.span {
+            //tabs are kept so the caret lines up with the text above
+            let padding: String = self
+                .line_text
+                .chars()
+                .take(span.start)
+                .map(|e| if e == '\t' { '\t' } else { ' ' })
+                .collect();
+            let carets = "^".repeat((span.end - span.start).max(1));
+            write!(f, "\n{gutter} | {padding}{carets}")?;
+        }
+        for note in self.notes.iter() {
+            write!(f, "\n{gutter} = note: {note}")?;
+        }
+        return Ok(());
+    }
+}
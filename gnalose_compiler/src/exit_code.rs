@@ -0,0 +1,40 @@
+use crate::diagnostic::Stage;
+
+///exit codes of the cli and of compiled programs, documented in README. Never reuse a number
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExitCode {
+    Success = 0,
+    Usage = 2,
+    Io = 3,
+    Lex = 4,
+    Parse = 5,
+    Compile = 6,
+    NativeBuild = 7,
+    ///"fmt --check" found unformatted source
+    Unformatted = 8,
+    //returned by the compiled program, "run" returns the same ones
+    Leak = 10,
+    UsedUndefined = 11,
+    NothingToPin = 12,
+    OutOfBounds = 13,
+    ///only with "--overflow trap"
+    Overflow = 14,
+    ///"print" found no number with "--on-bad-input abort" or "retry"
+    BadInput = 15,
+}
+
+impl ExitCode {
+    pub fn code(&self) -> i32 {
+        return *self as i32;
+    }
+}
+
+impl From<Stage> for ExitCode {
+    fn from(stage: Stage) -> Self {
+        return match stage {
+            Stage::Lexer => ExitCode::Lex,
+            Stage::Parser => ExitCode::Parse,
+            Stage::Compiler | Stage::Analysis => ExitCode::Compile,
+        };
+    }
+}
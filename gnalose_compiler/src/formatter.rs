@@ -0,0 +1,87 @@
+//source formatter, only whitespace, indentation and brackets change. Comments and the bottom to top order are kept
+
+use crate::{
+    cst::{Cst, CstLine},
+    representation::*,
+    token::{ParenthesisSide, Token},
+};
+
+const INDENT: &str = "\t";
+
+fn separator(previous: Option<&Token>) -> &'static str {
+    return match previous {
+        None | Some(Token::ArrayBracket(ParenthesisSide::Left)) => "",
+        Some(_) => " ",
+    };
+}
+
+//comments as they were, then code tokens separated by single space, array brackets without any
+fn format_line(line: &CstLine) -> String {
+    let mut formatted = String::new();
+    for comment in line.comments.iter() {
+        formatted += comment.as_str();
+        formatted += "/";
+    }
+    let mut previous: Option<&Token> = None;
+    for (token, text) in line.tokens.iter().zip(line.token_texts.iter()) {
+        match token {
+            Token::ArrayBracket(ParenthesisSide::Left) => formatted += "[",
+            Token::ArrayBracket(ParenthesisSide::Right) => formatted += "]",
+            Token::Name(name) => {
+                formatted += separator(previous);
+                formatted += name;
+            }
+            //char literal stays as it was written
+            Token::Literal(_) if text.starts_with('\'') => {
+                formatted += separator(previous);
+                formatted += text;
+            }
+            Token::Literal(literal) => {
+                formatted += separator(previous);
+                formatted += literal.to_string().as_str();
+            }
+            Token::Text(_) => {
+                formatted += separator(previous);
+                formatted += text;
+            }
+            Token::Comment(_) => {}
+        }
+        previous = Some(token);
+    }
+    return formatted;
+}
+
+///source formatted, lines inside of "if"..."fi" are indented with a tab (see examples/div.gnalose)
+pub fn format(cst: &Cst) -> String {
+    let newline = if cst.lines.iter().any(|e| e.line_ending == "\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+
+    let mut result: Vec<String> = Vec::new();
+    let mut depth: usize = 0;
+    for line in cst.lines.iter() {
+        if let Some(Op::Fi) = line.op {
+            depth = depth.saturating_sub(1);
+        }
+        if line.is_blank() {
+            result.push(String::new());
+        } else {
+            result.push(INDENT.repeat(depth) + format_line(line).as_str());
+        }
+        if let Some(Op::If(_, _, _)) = line.op {
+            depth += 1;
+        }
+    }
+    while result.last().is_some_and(|e| e.is_empty()) {
+        result.pop();
+    }
+
+    let mut formatted = if cst.bom { "\u{feff}".to_owned() } else { String::new() };
+    for line in result {
+        formatted += line.as_str();
+        formatted += newline;
+    }
+    return formatted;
+}
@@ -0,0 +1,85 @@
+//the only type of gnalose: signed integer of chosen width, lexer, interpreter and compiler all use the same one
+//values are kept as i64 cut to the width, the way unsigned arithmetic of the c type does it
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum)]
+pub enum IntWidth {
+    #[value(name = "8")]
+    W8,
+    #[value(name = "16")]
+    W16,
+    #[default]
+    #[value(name = "32")]
+    W32,
+    #[value(name = "64")]
+    W64,
+}
+
+impl IntWidth {
+    pub fn bits(&self) -> u32 {
+        return match self {
+            IntWidth::W8 => 8,
+            IntWidth::W16 => 16,
+            IntWidth::W32 => 32,
+            IntWidth::W64 => 64,
+        };
+    }
+    pub fn min(&self) -> i64 {
+        return i64::MIN >> (64 - self.bits());
+    }
+    pub fn max(&self) -> i64 {
+        return i64::MAX >> (64 - self.bits());
+    }
+    pub fn contains(&self, value: i128) -> bool {
+        return (self.min() as i128..=self.max() as i128).contains(&value);
+    }
+    ///value wrapped around to the width
+    pub fn wrap(&self, value: i64) -> i64 {
+        let shift = 64 - self.bits();
+        return (value << shift) >> shift;
+    }
+    ///a+b wrapped around to the width
+    pub fn wrapping_add(&self, a: i64, b: i64) -> i64 {
+        return self.wrap(a.wrapping_add(b));
+    }
+    ///a-b wrapped around to the width
+    pub fn wrapping_sub(&self, a: i64, b: i64) -> i64 {
+        return self.wrap(a.wrapping_sub(b));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum)]
+pub enum Overflow {
+    ///result wraps around, like unsigned arithmetic
+    #[default]
+    Wrap,
+    ///program aborts
+    Trap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, derive_new::new)]
+pub struct Integers {
+    pub width: IntWidth,
+    pub overflow: Overflow,
+}
+
+impl Integers {
+    //None when value doesn't fit and it has to trap
+    fn fit(&self, value: i128) -> Option<i64> {
+        if self.overflow == Overflow::Trap && !self.width.contains(value) {
+            return None;
+        }
+        return Some(self.width.wrap(value as i64));
+    }
+    ///a+b, None on overflow with Overflow::Trap
+    pub fn add(&self, a: i64, b: i64) -> Option<i64> {
+        return self.fit(a as i128 + b as i128);
+    }
+    ///a-b, None on overflow with Overflow::Trap
+    pub fn sub(&self, a: i64, b: i64) -> Option<i64> {
+        return self.fit(a as i128 - b as i128);
+    }
+    ///number read from the input, None when it doesn't fit with Overflow::Trap
+    pub fn from_input(&self, value: i64) -> Option<i64> {
+        return self.fit(value as i128);
+    }
+}
@@ -0,0 +1,523 @@
+//runs representation directly, mirroring the c emitted by the compiler
+//values are stored the same way as in c: as "value - global", so "add"/"sub" only have to touch global and the excluded ones
+
+use std::{
+    fmt::{self, Display},
+    io::{self, BufRead, Write},
+};
+
+use crate::{
+    cfg::Cfg,
+    compiler::CompilerError,
+    exit_code::ExitCode,
+    integer::{Integers, Overflow},
+    options::{BadInput, OptionFlags, Options},
+    representation::*,
+    utility::LinedError,
+};
+
+///line that failed, both numberings used by LinedError
+#[derive(Debug, Clone, Default)]
+pub struct Location {
+    pub line: usize,
+    pub line_from_bottom: usize,
+    pub line_text: String,
+}
+
+impl Location {
+    fn new(repr: &Representation, op: &OpLine) -> Location {
+        return Location {
+            line: repr.get_line_from_top(op),
+            line_from_bottom: op.line_num + 1,
+            line_text: op.line_text.trim().to_owned(),
+        };
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "at line {} (from bottom:{}): {}",
+            self.line, self.line_from_bottom, self.line_text
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum RuntimeError {
+    UsedUndefined(NameType, String, Location),
+    NothingToPin(Location),
+    MemoryLeak(String),
+    IndexOutOfBounds(String, i64, Location),
+    ///with the width in bits, only with Overflow::Trap
+    Overflow(u32, Location),
+    ///with what was expected, "number" or "byte"
+    BadInput(&'static str, Location),
+}
+
+#[derive(Debug)]
+pub enum RunError {
+    Structure(Vec<LinedError<CompilerError>>),
+    Runtime(RuntimeError),
+    Io(io::Error),
+}
+
+impl RuntimeError {
+    ///same as the compiled program would exit with
+    pub fn exit_code(&self) -> ExitCode {
+        return match self {
+            RuntimeError::UsedUndefined(_, _, _) => ExitCode::UsedUndefined,
+            RuntimeError::NothingToPin(_) => ExitCode::NothingToPin,
+            RuntimeError::MemoryLeak(_) => ExitCode::Leak,
+            RuntimeError::IndexOutOfBounds(_, _, _) => ExitCode::OutOfBounds,
+            RuntimeError::Overflow(_, _) => ExitCode::Overflow,
+            RuntimeError::BadInput(_, _) => ExitCode::BadInput,
+        };
+    }
+}
+
+impl Display for RuntimeError {
+    //same texts as the ones printed by compiled program
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::UsedUndefined(kind, name, location) => write!(
+                f,
+                "Tried to use already undefined {} \"{}\"\n{}",
+                kind.describe(),
+                name,
+                location
+            ),
+            RuntimeError::NothingToPin(location) => {
+                write!(f, "Nothing to pin, \"halt\" used before any \"forget\"\n{}", location)
+            }
+            RuntimeError::MemoryLeak(name) => write!(
+                f,
+                "Memory leaked: {}. Everything should be undefined at the end using \"define\"",
+                name
+            ),
+            RuntimeError::IndexOutOfBounds(name, index, location) => {
+                write!(f, "Index {} is out of bounds of array \"{}\"\n{}", index, name, location)
+            }
+            RuntimeError::Overflow(bits, location) => {
+                write!(
+                    f,
+                    "Integer overflow, result doesn't fit in {}-bit integer\n{}",
+                    bits, location
+                )
+            }
+            RuntimeError::BadInput(expected, location) => {
+                write!(f, "No {} to read on the input\n{}", expected, location)
+            }
+        }
+    }
+}
+
+impl Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::Structure(errors) => {
+                for err in errors {
+                    writeln!(f, "{}", err)?;
+                }
+                Ok(())
+            }
+            RunError::Runtime(err) => write!(f, "ABORTED\n{}", err),
+            RunError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<io::Error> for RunError {
+    fn from(err: io::Error) -> Self {
+        return RunError::Io(err);
+    }
+}
+impl From<RuntimeError> for RunError {
+    fn from(err: RuntimeError) -> Self {
+        return RunError::Runtime(err);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Slot {
+    Variable(usize),
+    ArrayElement(usize, usize),
+}
+
+struct State<'a> {
+    repr: &'a Representation,
+    integers: Integers,
+    global: i64,
+    variables: Vec<i64>,
+    variables_on: Vec<bool>,
+    arrays: Vec<Vec<i64>>,
+    arrays_on: Vec<bool>,
+    flags_on: Vec<bool>,
+    label: Option<usize>,
+    location: Location,
+}
+
+impl<'a> State<'a> {
+    fn new(repr: &'a Representation) -> State<'a> {
+        return State {
+            repr,
+            integers: repr.integers,
+            global: 0,
+            variables: vec![0; repr.variables_names.len()],
+            variables_on: vec![false; repr.variables_names.len()],
+            arrays: repr.array_names.iter().map(|e| vec![0; e.1]).collect(),
+            arrays_on: vec![false; repr.array_names.len()],
+            flags_on: vec![true; repr.flags_names.len()],
+            label: None,
+            location: Location::default(),
+        };
+    }
+
+    //result of integer math, None is an overflow that has to trap
+    fn checked(&self, result: Option<i64>) -> Result<i64, RuntimeError> {
+        return result.ok_or(RuntimeError::Overflow(self.integers.width.bits(), self.location.clone()));
+    }
+
+    //global and raw values always wrap, only visible values can overflow
+    fn get(&self, raw: i64) -> i64 {
+        return self.integers.width.wrapping_add(raw, self.global);
+    }
+
+    //raw value of just defined variable, so that its value is 0
+    fn negated_global(&self) -> i64 {
+        return self.integers.width.wrapping_sub(0, self.global);
+    }
+
+    //a+b or a-b, same as add/sub of c
+    fn apply(&self, a: i64, b: i64, plus: bool) -> i64 {
+        return if plus {
+            self.integers.width.wrapping_add(a, b)
+        } else {
+            self.integers.width.wrapping_sub(a, b)
+        };
+    }
+
+    //index is not asserted nor sheltered, same as in c
+    fn index_value(&self, index: IValue) -> Result<i64, RuntimeError> {
+        return match index {
+            IValue::LValue(l) => Ok(l),
+            IValue::RValue(r) => Ok(self.get(self.variables[r.0])),
+        };
+    }
+
+    fn slot(&self, id: VValue) -> Result<Slot, RuntimeError> {
+        return match id {
+            VValue::RValue(r) => Ok(Slot::Variable(r.0)),
+            VValue::ArrayElement(el) => {
+                let index = self.index_value(el.index)?;
+                if index < 0 || index as usize >= self.repr.get_array_size(el.array_ref) {
+                    let name = self.repr.get_array_name(el.array_ref);
+                    return Err(RuntimeError::IndexOutOfBounds(name, index, self.location.clone()));
+                }
+                Ok(Slot::ArrayElement(el.array_ref.0, index as usize))
+            }
+        };
+    }
+
+    fn raw(&self, slot: Slot) -> i64 {
+        return match slot {
+            Slot::Variable(id) => self.variables[id],
+            Slot::ArrayElement(id, index) => self.arrays[id][index],
+        };
+    }
+
+    fn raw_mut(&mut self, slot: Slot) -> &mut i64 {
+        return match slot {
+            Slot::Variable(id) => &mut self.variables[id],
+            Slot::ArrayElement(id, index) => &mut self.arrays[id][index],
+        };
+    }
+
+    fn assert_alive(&self, id: AValue) -> Result<(), RuntimeError> {
+        let (alive, kind, name) = match id {
+            AValue::LValue(_) => return Ok(()),
+            AValue::RValue(r) => (self.variables_on[r.0], NameType::Variable, self.repr.get_variable_name(r)),
+            AValue::ArrayElement(el) => (
+                self.arrays_on[el.array_ref.0],
+                NameType::Array,
+                self.repr.get_array_name(el.array_ref),
+            ),
+        };
+        if !alive {
+            return Err(RuntimeError::UsedUndefined(kind, name, self.location.clone()));
+        }
+        return Ok(());
+    }
+
+    fn value(&mut self, a: AValue) -> Result<i64, RuntimeError> {
+        return match a {
+            AValue::LValue(l) => Ok(l),
+            AValue::RValue(_) | AValue::ArrayElement(_) => {
+                let slot = self.slot(VValue::try_from(a).unwrap())?;
+                Ok(self.get(self.raw(slot)))
+            }
+        };
+    }
+
+    fn math(&mut self, a: AValue, b: VValue, plus: bool) -> Result<(), RuntimeError> {
+        self.assert_alive(a)?;
+        self.assert_alive(AValue::from(b))?;
+        let temp = self.value(a)?;
+
+        // address of b is taken before global changes, a is looked up again after it (see compile_math_line)
+        let addr_b = self.slot(b)?;
+        self.global = self.apply(self.global, temp, plus);
+        *self.raw_mut(addr_b) = self.apply(self.raw(addr_b), temp, !plus);
+
+        let mut addr_a = None;
+        if let AValue::RValue(_) | AValue::ArrayElement(_) = a {
+            let addr = self.slot(VValue::try_from(a).unwrap())?;
+            *self.raw_mut(addr) = self.apply(self.raw(addr), temp, !plus);
+            addr_a = Some(addr);
+        }
+        if self.integers.overflow == Overflow::Trap {
+            self.check_values(temp, plus, addr_b, addr_a)?;
+        }
+        return Ok(());
+    }
+
+    //with Overflow::Trap every defined value that changed by temp has to still fit, same as CHECK_VALUES in c
+    fn check_values(&self, temp: i64, plus: bool, b: Slot, a: Option<Slot>) -> Result<(), RuntimeError> {
+        let variables = (0..self.variables.len())
+            .filter(|e| self.variables_on[*e])
+            .map(Slot::Variable);
+        let elements = (0..self.arrays.len())
+            .filter(|e| self.arrays_on[*e])
+            .flat_map(|e| (0..self.arrays[e].len()).map(move |index| Slot::ArrayElement(e, index)));
+        for slot in variables.chain(elements) {
+            let (sheltered_b, sheltered_a) = (slot == b, Some(slot) == a);
+            //sheltered once didn't change at all, sheltered twice ("add a to a") changed the other way
+            if sheltered_b != sheltered_a {
+                continue;
+            }
+            let plus = plus != sheltered_b;
+            let new = self.get(self.raw(slot));
+            let old = self.apply(new, temp, !plus);
+            self.checked(if plus {
+                self.integers.add(old, temp)
+            } else {
+                self.integers.sub(old, temp)
+            })?;
+        }
+        return Ok(());
+    }
+
+    fn leak_check(&self) -> Result<(), RuntimeError> {
+        let vars = self.repr.variables_names.iter().zip(self.variables_on.iter());
+        let arrays = self.repr.array_names.iter().map(|e| &e.0).zip(self.arrays_on.iter());
+        let flags = self.repr.flags_names.iter().zip(self.flags_on.iter());
+
+        if let Some((name, _)) = vars.chain(arrays).chain(flags).find(|e| *e.1) {
+            return Err(RuntimeError::MemoryLeak(name.clone()));
+        }
+        return Ok(());
+    }
+}
+
+fn is_condition_met(a: i64, b: i64, cond: ConditionType) -> bool {
+    return match cond {
+        ConditionType::Equal => a == b,
+        ConditionType::NotEqual => a != b,
+        ConditionType::Greater => a > b,
+        ConditionType::Less => a < b,
+        ConditionType::GreaterOrEqual => a >= b,
+        ConditionType::LessOrEqual => a <= b,
+    };
+}
+
+fn peek_byte<R: BufRead>(input: &mut R) -> io::Result<Option<u8>> {
+    return Ok(input.fill_buf()?.first().copied());
+}
+
+//behaves like scanf("%lld"), None when nothing could be read
+fn scan_int<R: BufRead>(input: &mut R) -> io::Result<Option<i64>> {
+    while let Some(byte) = peek_byte(input)? {
+        if !byte.is_ascii_whitespace() {
+            break;
+        }
+        input.consume(1);
+    }
+    let mut negative = false;
+    if let Some(sign @ (b'-' | b'+')) = peek_byte(input)? {
+        negative = sign == b'-';
+        input.consume(1);
+    }
+    let mut number: Option<i64> = None;
+    while let Some(byte) = peek_byte(input)? {
+        if !byte.is_ascii_digit() {
+            break;
+        }
+        //too long number saturates like strtoll, negative one is built negative so that i64::MIN fits
+        let digit = (byte - b'0') as i64;
+        let shifted = number.unwrap_or(0).saturating_mul(10);
+        number = Some(if negative {
+            shifted.saturating_sub(digit)
+        } else {
+            shifted.saturating_add(digit)
+        });
+        input.consume(1);
+    }
+    return Ok(number);
+}
+
+//number for "print", None when it has to abort
+fn read_number<R: BufRead>(input: &mut R, arg: &Options) -> io::Result<Option<i64>> {
+    loop {
+        if let Some(number) = scan_int(input)? {
+            return Ok(Some(number));
+        }
+        match arg.on_bad_input {
+            BadInput::Abort => return Ok(None),
+            //so the next "print" doesn't get stuck on the same token
+            BadInput::Zero => {
+                skip_line(input)?;
+                return Ok(Some(0));
+            }
+            BadInput::Retry => {
+                //nothing left, like scanf returning EOF
+                if peek_byte(input)?.is_none() {
+                    return Ok(None);
+                }
+                skip_line(input)?;
+            }
+        }
+    }
+}
+
+//like scanf("%*[^\n]"), the newline stays
+fn skip_line<R: BufRead>(input: &mut R) -> io::Result<()> {
+    while let Some(byte) = peek_byte(input)? {
+        if byte == b'\n' {
+            break;
+        }
+        input.consume(1);
+    }
+    return Ok(());
+}
+
+//byte for "print as number", only the end of the input is bad
+fn read_byte<R: BufRead>(input: &mut R, arg: &Options) -> io::Result<Option<i64>> {
+    if let Some(byte) = peek_byte(input)? {
+        input.consume(1);
+        return Ok(Some(byte as i64));
+    }
+    return Ok((arg.on_bad_input == BadInput::Zero).then_some(0));
+}
+
+//value of "read as number to" as raw byte or as utf-8 encoded code point, invalid ones are U+FFFD like in c
+fn encode_char(value: i64, arg: &Options) -> Vec<u8> {
+    if !arg.flags.contains(OptionFlags::Utf8Output) {
+        return vec![value as u8];
+    }
+    let chr = u32::try_from(value)
+        .ok()
+        .and_then(char::from_u32)
+        .unwrap_or(char::REPLACEMENT_CHARACTER);
+    return chr.to_string().into_bytes();
+}
+
+fn execute<R: BufRead, W: Write>(
+    repr: &Representation,
+    cfg: &Cfg,
+    arg: &Options,
+    input: &mut R,
+    output: &mut W,
+) -> Result<(), RunError> {
+    let mut state = State::new(repr);
+
+    let mut i = 0;
+    while i < repr.ops.len() {
+        state.location = Location::new(repr, &repr.ops[i]);
+        match repr.ops[i].op {
+            Op::Define(id) => {
+                state.variables[id.0] = state.negated_global();
+                state.variables_on[id.0] = true;
+            }
+            Op::DefineArray(id) => {
+                let value = state.negated_global();
+                state.arrays[id.0].fill(value);
+                state.arrays_on[id.0] = true;
+            }
+            Op::Undefine(id) => state.variables_on[id.0] = false,
+            Op::UndefineArray(id) => state.arrays_on[id.0] = false,
+            Op::Read(id) | Op::ReadASCII(id) => {
+                state.assert_alive(AValue::from(id))?;
+                let slot = state.slot(id)?;
+                output.flush()?;
+                let (value, expected) = match repr.ops[i].op {
+                    Op::Read(_) => (read_number(input, arg)?, "number"),
+                    _ => (read_byte(input, arg)?, "byte"),
+                };
+                let value = value.ok_or(RuntimeError::BadInput(expected, state.location.clone()))?;
+                let value = state.checked(state.integers.from_input(value))?;
+                *state.raw_mut(slot) = state.apply(value, state.global, false);
+            }
+            Op::Print(val) => {
+                state.assert_alive(val)?;
+                writeln!(output, "{}", state.value(val)?)?;
+            }
+            Op::PrintASCII(val) => {
+                state.assert_alive(val)?;
+                output.write_all(&encode_char(state.value(val)?, arg))?;
+            }
+            Op::PrintText(ref text) => output.write_all(text.as_bytes())?,
+            Op::Add(a, b) => state.math(a, b, true)?,
+            Op::Subtract(a, b) => state.math(a, b, false)?,
+            Op::Mark(_) => {}
+            Op::Unmark(flag) => state.flags_on[flag.0] = false,
+            Op::Pin(flag) => {
+                if !state.flags_on[flag.0] {
+                    let name = repr.get_flag_name(flag);
+                    return Err(RuntimeError::UsedUndefined(NameType::Flag, name, state.location.clone()).into());
+                }
+                state.label = Some(cfg.marks[flag.0]);
+            }
+            Op::Goto => {
+                i = state.label.ok_or(RuntimeError::NothingToPin(state.location.clone()))?;
+                continue;
+            }
+            Op::Fi => {
+                let if_index = cfg.matching[i].unwrap();
+                state.location = Location::new(repr, &repr.ops[if_index]);
+                if let Op::If(a, b, cond) = repr.ops[if_index].op {
+                    let (a, b) = (state.value(a)?, state.value(b)?);
+                    if !is_condition_met(a, b, cond) {
+                        i = if_index;
+                    }
+                }
+            }
+            Op::If(_, _, _) => {}
+        }
+        i += 1;
+    }
+    state.leak_check()?;
+    return Ok(());
+}
+
+///run_with_args with the default options
+pub fn run<R: BufRead, W: Write>(repr: &Representation, input: &mut R, output: &mut W) -> Result<(), RunError> {
+    return run_with_args(repr, &Options::default(), input, output);
+}
+
+///runs the program, runtime errors are also written to output the same way compiled program does
+///only options changing the behaviour of compiled program matter (e.g. Utf8Output or on_bad_input)
+pub fn run_with_args<R: BufRead, W: Write>(
+    repr: &Representation,
+    arg: &Options,
+    input: &mut R,
+    output: &mut W,
+) -> Result<(), RunError> {
+    let cfg = Cfg::build(repr).map_err(RunError::Structure)?;
+    let result = execute(repr, &cfg, arg, input, output);
+    if let Err(RunError::Runtime(err)) = &result {
+        write!(output, "ABORTED\n{}", err)?;
+    }
+    output.flush()?;
+    return result;
+}
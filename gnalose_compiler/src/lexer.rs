@@ -1,18 +1,137 @@
-use std::num::ParseIntError;
+use std::fmt::{self, Display};
 
-use crate::{token::*, utility::LinedError};
+use crate::{
+    diagnostic::{DiagnosticSource, ErrorCode},
+    integer::IntWidth,
+    token::*,
+    utility::LinedError,
+};
+
+//invalid token with its text, span is added by tokenize_line_spanned
+type TokenError = (LexingErrorKind, String);
+
+#[derive(Debug)]
+pub enum LexingErrorKind {
+    ///literal doesn't fit in the integer width
+    OutOfRange(IntWidth),
+    ///"-" without digits right after it
+    LoneMinus,
+    ///quote without closing one on the same line
+    Unterminated,
+    InvalidEscape(char),
+    ///char literal with zero or more than one char
+    NotSingleChar,
+}
+
+#[derive(Debug, derive_new::new)]
+pub struct LexingError {
+    pub error: LexingErrorKind,
+    pub text: String,
+    pub span: Span,
+}
+
+impl Display for LexingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error {
+            LexingErrorKind::OutOfRange(width) => write!(f, "{} doesn't fit in {}-bit integer", self.text, width.bits()),
+            LexingErrorKind::LoneMinus => write!(f, "\"-\" has to be followed by a number"),
+            LexingErrorKind::Unterminated => write!(f, "{} is never closed", self.text),
+            LexingErrorKind::InvalidEscape(chr) => write!(f, "\"\\{}\" in {} is not a valid escape", chr, self.text),
+            LexingErrorKind::NotSingleChar => write!(f, "{} has to contain exactly one character", self.text),
+        }
+    }
+}
+
+impl DiagnosticSource for LexingError {
+    fn code(&self) -> ErrorCode {
+        return ErrorCode::InvalidLiteral;
+    }
+    fn notes(&self) -> Vec<String> {
+        return match self.error {
+            LexingErrorKind::OutOfRange(width) => vec![
+                format!("the only type is integer from {} to {}", width.min(), width.max()),
+                "integer width is set with --int-width".to_owned(),
+            ],
+            LexingErrorKind::LoneMinus => vec!["negative number is written without space, e.g. \"-5\"".to_owned()],
+            LexingErrorKind::Unterminated => vec!["quote has to be closed on the same line".to_owned()],
+            LexingErrorKind::InvalidEscape(_) => {
+                vec!["valid escapes are \\n, \\t, \\\\, \\', \\\" and \\0 in char literal".to_owned()]
+            }
+            LexingErrorKind::NotSingleChar => vec!["use \"read as text to\" to print more characters".to_owned()],
+        };
+    }
+    fn span(&self, _line_text: &str) -> Option<Span> {
+        return Some(self.span.clone());
+    }
+}
 
 fn is_number(chr: char) -> bool {
-    return chr >= '0' && chr <= '9';
+    return chr.is_ascii_digit();
+}
+
+fn is_quote(chr: char) -> bool {
+    return chr == '\'' || chr == '"';
+}
+
+//length of quoted literal starting at chars[0], None when it's never closed
+fn find_closing_quote(chars: &[char]) -> Option<usize> {
+    let mut i = 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            chr if chr == chars[0] => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    return None;
+}
+
+//content of quoted literal, without the quotes
+fn unescape(literal: &[char]) -> Result<String, TokenError> {
+    let mut result = String::new();
+    let mut chars = literal[1..literal.len() - 1].iter();
+    while let Some(&chr) = chars.next() {
+        if chr != '\\' {
+            result.push(chr);
+            continue;
+        }
+        let escaped = *chars.next().unwrap();
+        result.push(match escaped {
+            'n' => '\n',
+            't' => '\t',
+            //fputs would stop at it
+            '0' if literal[0] == '\'' => '\0',
+            '\\' | '\'' | '"' => escaped,
+            _ => return Err((LexingErrorKind::InvalidEscape(escaped), literal.iter().collect())),
+        });
+    }
+    return Ok(result);
+}
+
+//"/" inside of closed quote doesn't end the comment
+fn find_comment_end(chars: &[char]) -> Option<usize> {
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '/' {
+            return Some(i);
+        }
+        let starts_token = i == 0 || chars[i - 1].is_whitespace() || chars[i - 1] == '[';
+        if let Some(length) = find_closing_quote(&chars[i..]).filter(|_| is_quote(chars[i]) && starts_token) {
+            i += length;
+            continue;
+        }
+        i += 1;
+    }
+    return None;
 }
 
 //assumes no comment
-fn tokenize_simple_next(chars: &[char]) -> Result<Option<(&[char], Token)>, (ParseIntError, String)> {
+fn tokenize_simple_next(chars: &[char], width: IntWidth) -> Result<Option<(&[char], Token)>, TokenError> {
     let mut chars = chars;
-    while chars.len() > 0 && chars[0].is_whitespace() {
+    while !chars.is_empty() && chars[0].is_whitespace() {
         chars = &chars[1..]
     }
-    if chars.len() == 0 {
+    if chars.is_empty() {
         return Ok(None);
     }
 
@@ -23,38 +142,72 @@ fn tokenize_simple_next(chars: &[char]) -> Result<Option<(&[char], Token)>, (Par
         return Ok(Some((&chars[1..], Token::ArrayBracket(ParenthesisSide::Right))));
     }
 
-    if is_number(chars[0]) {
+    //'a' is a literal with its code point, "text" is only used by "read as text to"
+    if is_quote(chars[0]) {
+        let Some(length) = find_closing_quote(chars) else {
+            return Err((LexingErrorKind::Unterminated, chars[0].to_string()));
+        };
+        let text = unescape(&chars[..length])?;
+        if chars[0] == '"' {
+            return Ok(Some((&chars[length..], Token::Text(text))));
+        }
+        let mut text_chars = text.chars();
+        let literal: String = chars[..length].iter().collect();
+        return match (text_chars.next(), text_chars.next()) {
+            (Some(chr), None) if width.contains(chr as i128) => Ok(Some((&chars[length..], Token::Literal(chr as i64)))),
+            (Some(_), None) => Err((LexingErrorKind::OutOfRange(width), literal)),
+            _ => Err((LexingErrorKind::NotSingleChar, literal)),
+        };
+    }
+
+    //names can contain "-" too, only "-" followed by digit starts a number
+    let is_negative = chars[0] == '-' && chars.get(1).is_some_and(|&e| is_number(e));
+    if is_number(chars[0]) || is_negative {
         let mut i = 1;
         while (i) < chars.len() && is_number(chars[i]) {
             i += 1;
         }
-        let number_as_text = chars[0..i].into_iter().collect::<String>();
-        let number = number_as_text.parse::<i32>().map_err(|er| (er, number_as_text))?;
+        let number_as_text = chars[0..i].iter().collect::<String>();
+        //only digits, so it can only fail by being too long
+        let Some(number) = number_as_text.parse::<i128>().ok().filter(|e| width.contains(*e)) else {
+            return Err((LexingErrorKind::OutOfRange(width), number_as_text));
+        };
 
-        return Ok(Some((&chars[i..], Token::Literal(number))));
+        return Ok(Some((&chars[i..], Token::Literal(number as i64))));
+    }
+    let ends_name = |chr: &char| chr.is_whitespace() || *chr == '[' || *chr == ']';
+    if chars[0] == '-' && chars.get(1).is_none_or(ends_name) {
+        return Err((LexingErrorKind::LoneMinus, "-".to_owned()));
     }
 
-    let i = chars
-        .iter()
-        .position(|&el| el.is_whitespace() || el == '[' || el == ']')
-        .unwrap_or(chars.len());
+    let i = chars.iter().position(ends_name).unwrap_or(chars.len());
 
     let result: String = chars[0..i].iter().collect();
-    if i + 1 <= chars.len() {
+    if i < chars.len() {
         return Ok(Some((&chars[i..], Token::Name(result))));
     } else {
         return Ok(Some((&[], Token::Name(result))));
     };
 }
 
-pub fn tokenize_line(txt: &str) -> Result<Vec<Token>, (ParseIntError, String)> {
+///literals only have to fit in 64 bits, meant for lines already checked by tokenize
+pub fn tokenize_line(txt: &str) -> Result<Vec<Token>, LexingError> {
+    return Ok(tokenize_line_spanned(txt, IntWidth::W64)?.0);
+}
+
+///tokens with their char positions inside the line
+pub fn tokenize_line_spanned(txt: &str, width: IntWidth) -> Result<(Vec<Token>, Vec<Span>), LexingError> {
     let mut tokens: Vec<Token> = Vec::new();
-    let mut chars: &[char] = &txt.chars().collect::<Vec<char>>();
+    let mut spans: Vec<Span> = Vec::new();
+    let all_chars = txt.chars().collect::<Vec<char>>();
+    let mut chars: &[char] = &all_chars;
+    let offset = |chars: &[char]| all_chars.len() - chars.len();
 
     loop {
-        let comment_index = chars.iter().position(|&el| el == '/');
+        let comment_index = find_comment_end(chars);
         if let Some(pos) = comment_index {
             tokens.push(Token::Comment(chars[0..pos].iter().collect()));
+            spans.push(offset(chars)..offset(chars) + pos);
             chars = &chars[pos + 1..];
         } else {
             break;
@@ -62,30 +215,66 @@ pub fn tokenize_line(txt: &str) -> Result<Vec<Token>, (ParseIntError, String)> {
     }
 
     loop {
-        let next = tokenize_simple_next(chars)?;
+        while !chars.is_empty() && chars[0].is_whitespace() {
+            chars = &chars[1..]
+        }
+        let start = offset(chars);
+        let next = tokenize_simple_next(chars, width).map_err(|err| {
+            let end = start + err.1.chars().count();
+            LexingError::new(err.0, err.1, start..end)
+        })?;
         match next {
             Some((text, token)) => {
                 chars = text;
                 tokens.push(token);
+                spans.push(start..offset(chars));
             }
             None => {
                 break;
             }
         }
     }
-    return Ok(tokens);
+    return Ok((tokens, spans));
+}
+
+///tokenize_with_width for the default 32-bit integer
+pub fn tokenize(txt: &str) -> Result<Vec<TokenLine>, Vec<LinedError<LexingError>>> {
+    return tokenize_with_width(txt, IntWidth::default());
 }
 
-pub fn tokenize(txt: &str) -> Result<Vec<TokenLine>, LinedError<ParseIntError>> {
+///every line is tokenized, even after error, so all of them are reported
+pub fn tokenize_with_width(txt: &str, width: IntWidth) -> Result<Vec<TokenLine>, Vec<LinedError<LexingError>>> {
+    let (vec, errors) = tokenize_partial(txt, width);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    return Ok(vec);
+}
+
+///lines that lexed and errors of the rest, failed lines are left without tokens so numbering doesn't change
+pub fn tokenize_partial(txt: &str, width: IntWidth) -> (Vec<TokenLine>, Vec<LinedError<LexingError>>) {
     let mut vec: Vec<TokenLine> = Vec::new();
-    let mut i = 1;
+    let mut errors = Vec::new();
+    //files saved by some windows editors start with byte order mark
+    let txt = txt.strip_prefix('\u{feff}').unwrap_or(txt);
 
-    for line_content in txt.lines().rev() {
-        let tokenized =
-            tokenize_line(line_content.trim()).map_err(|err| LinedError::new(i, txt.lines().count(), err.1, err.0))?;
+    for (i, line_content) in txt.lines().rev().enumerate() {
+        //spans are relative to the whole line, not the trimmed one
+        let indent = line_content.chars().count() - line_content.trim_start().chars().count();
+        let shift = |span: Span| span.start + indent..span.end + indent;
 
-        vec.push(TokenLine::new(tokenized, line_content.to_owned()));
-        i += 1;
+        match tokenize_line_spanned(line_content.trim(), width) {
+            Ok((tokens, spans)) => vec.push(TokenLine::new(
+                tokens,
+                spans.into_iter().map(shift).collect(),
+                line_content.to_owned(),
+            )),
+            Err(mut err) => {
+                err.span = shift(err.span.clone());
+                errors.push(LinedError::new(i + 1, txt.lines().count(), line_content.to_owned(), err));
+                vec.push(TokenLine::new(vec![], vec![], line_content.to_owned()));
+            }
+        }
     }
-    return Ok(vec);
+    return (vec, errors);
 }
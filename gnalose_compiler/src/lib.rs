@@ -0,0 +1,148 @@
+use std::time::Instant;
+
+pub mod analysis;
+pub mod cfg;
+pub mod compiler;
+pub mod cst;
+pub mod diagnostic;
+pub mod exit_code;
+pub mod formatter;
+pub mod integer;
+pub mod interpreter;
+pub mod lexer;
+pub mod lint;
+pub mod native;
+pub mod optimizer;
+pub mod options;
+pub mod parser;
+pub mod representation;
+mod string_builder;
+pub mod token;
+pub mod utility;
+
+#[cfg(test)]
+//tests keep their own "mod test" inside of the file
+#[allow(clippy::module_inception)]
+mod test;
+
+pub use diagnostic::{Diagnostic, Severity, Stage};
+pub use exit_code::ExitCode;
+pub use options::{BadInput, OptionFlags, Options};
+pub use representation::{Op, OpLine, Representation};
+
+///tokenizes and parses, verbose/intermediate output goes to out_func depending on options
+///lines are independent, so on error every line that lexed is parsed and blocks of what parsed are matched,
+///all the errors are returned together, sorted in source order
+pub fn parse_gnalose_with_args<F>(code: &str, arg: &Options, out_func: &F) -> Result<Representation, Vec<Diagnostic>>
+where
+    F: Fn(&str),
+{
+    let is_verbose = arg.flags.contains(OptionFlags::Verbose);
+    let is_print_everything = arg.flags.contains(OptionFlags::PrintEverything);
+
+    let bef = Instant::now();
+    let (tokens, lexing_errors) = lexer::tokenize_partial(code, arg.integers.width);
+
+    if is_verbose {
+        out_func(format!("TOKENIZATION DONE in {} s", bef.elapsed().as_secs_f32()).as_str());
+    }
+    if is_print_everything {
+        out_func(format!("Tokenization Output:\n.{}", token::format_token_collection(tokens.as_slice())).as_str());
+    }
+
+    let bef = Instant::now();
+    let (result, parsing_errors) = parser::parse_to_repr_partial(&tokens, arg.integers);
+    if !lexing_errors.is_empty() || !parsing_errors.is_empty() {
+        let mut diagnostics = Diagnostic::from_lined_all(Stage::Lexer, lexing_errors);
+        diagnostics.extend(Diagnostic::from_lined_all(Stage::Parser, parsing_errors));
+        if let Err(errors) = compiler::match_blocks(&result) {
+            diagnostics.extend(Diagnostic::from_lined_all(Stage::Compiler, errors));
+        }
+        //stable, so on the same line earlier stage goes first
+        diagnostics.sort_by_key(|e| e.get_line_from_top());
+        return Err(with_file_name(diagnostics, arg));
+    }
+
+    if is_verbose {
+        out_func(format!("PARSING DONE in {} s", bef.elapsed().as_secs_f32()).as_str());
+    }
+    if is_print_everything {
+        out_func(format!("Parsing Output:\n{}", parser::format_representation(&result)).as_str());
+    }
+    return Ok(result);
+}
+
+///whole pipeline from gnalose source to c source, verbose/intermediate output and warnings go to out_func
+///errors are returned the same way as by parse_gnalose_with_args. With DenyLeaks warnings are errors too
+pub fn compile_gnalose_to_c_with_args<F>(code: &str, arg: &Options, out_func: F) -> Result<String, Vec<Diagnostic>>
+where
+    F: Fn(&str),
+{
+    let is_verbose = arg.flags.contains(OptionFlags::Verbose);
+    let is_print_everything = arg.flags.contains(OptionFlags::PrintEverything);
+
+    let result = parse_gnalose_with_args(code, arg, &out_func)?;
+    analyze_with_args(&result, arg, &out_func)?;
+
+    let bef = Instant::now();
+    let result =
+        compiler::compile(&result, arg).map_err(|err| with_file_name(Diagnostic::from_lined_all(Stage::Compiler, err), arg))?;
+
+    if is_verbose {
+        out_func(format!("FINAL COMPILATION STEP DONE IN {} S\n", bef.elapsed().as_secs_f32()).as_str());
+    }
+    if is_print_everything {
+        out_func(format!("final compilation output:\n{}", result.as_str()).as_str());
+    }
+    return Ok(result);
+}
+
+///leak analysis and lints, warnings go to out_func. With DenyLeaks leak warnings are returned as errors instead
+pub fn analyze_with_args<F>(repr: &Representation, arg: &Options, out_func: &F) -> Result<(), Vec<Diagnostic>>
+where
+    F: Fn(&str),
+{
+    let warnings = analysis::check_leaks(repr);
+    let warnings = with_file_name(Diagnostic::from_lined_all(Stage::Analysis, warnings), arg);
+    if arg.flags.contains(OptionFlags::DenyLeaks) && !warnings.is_empty() {
+        return Err(warnings);
+    }
+    //lints never fail
+    let lints = with_file_name(Diagnostic::from_lined_all(Stage::Analysis, lint::lint(repr)), arg);
+    for warning in warnings.into_iter().chain(lints) {
+        out_func(format!("{}\n", warning.with_severity(Severity::Warning)).as_str());
+    }
+    return Ok(());
+}
+
+///every line with its comments and op, see cst::Cst
+pub fn parse_cst_with_args(code: &str, arg: &Options) -> Result<cst::Cst, Vec<Diagnostic>> {
+    let repr = parse_gnalose_with_args(code, arg, &|_| {})?;
+    return Ok(cst::Cst::build(code, repr));
+}
+
+///formatted source, only code that parses is formatted
+pub fn format_gnalose_with_args(code: &str, arg: &Options) -> Result<String, Vec<Diagnostic>> {
+    return Ok(formatter::format(&parse_cst_with_args(code, arg)?));
+}
+
+///only the first error in source order, see parse_gnalose_with_args for all of them
+pub fn parse_str(code: &str, options: Options) -> Result<Representation, Diagnostic> {
+    return parse_gnalose_with_args(code, &options, &|_| {}).map_err(first_diagnostic);
+}
+
+///only the first error in source order, see compile_gnalose_to_c_with_args for all of them
+pub fn compile_str(code: &str, options: Options) -> Result<String, Diagnostic> {
+    return compile_gnalose_to_c_with_args(code, &options, |_| {}).map_err(first_diagnostic);
+}
+
+fn with_file_name(diagnostics: Vec<Diagnostic>, arg: &Options) -> Vec<Diagnostic> {
+    return match &arg.file_name {
+        Some(file) => diagnostics.into_iter().map(|e| e.with_file(file)).collect(),
+        None => diagnostics,
+    };
+}
+
+fn first_diagnostic(diagnostics: Vec<Diagnostic>) -> Diagnostic {
+    return diagnostics.into_iter().next().unwrap();
+}
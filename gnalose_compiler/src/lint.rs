@@ -0,0 +1,197 @@
+//warnings about code that does nothing useful: ops that never run, labels never pinned and variables never read
+//every warning can be silenced with "allow NAME/" comment on the reported line, e.g. "allow never-read/undefine x"
+
+use std::fmt::{self, Display};
+
+use crate::{
+    cfg::Cfg,
+    diagnostic::{DiagnosticSource, ErrorCode},
+    lexer,
+    representation::*,
+    token::Token,
+    utility::LinedError,
+};
+
+#[derive(Debug)]
+pub enum LintWarning {
+    ///amount of ops in the unreachable part
+    Unreachable(usize),
+    NeverPinned(String),
+    NeverRead(String),
+}
+
+impl LintWarning {
+    ///used in "allow" comments
+    pub fn name(&self) -> &'static str {
+        return match self {
+            LintWarning::Unreachable(_) => "unreachable",
+            LintWarning::NeverPinned(_) => "never-pinned",
+            LintWarning::NeverRead(_) => "never-read",
+        };
+    }
+}
+
+impl Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintWarning::Unreachable(1) => write!(f, "this op can never run"),
+            LintWarning::Unreachable(amount) => write!(f, "this op and {} more after it can never run", amount - 1),
+            LintWarning::NeverPinned(name) => write!(f, "label \"{}\" is never pinned, so nothing jumps to it", name),
+            LintWarning::NeverRead(name) => write!(f, "variable \"{}\" is defined, but its value is never read", name),
+        }
+    }
+}
+
+impl DiagnosticSource for LintWarning {
+    fn code(&self) -> ErrorCode {
+        return match self {
+            LintWarning::Unreachable(_) => ErrorCode::Unreachable,
+            LintWarning::NeverPinned(_) => ErrorCode::NeverPinned,
+            LintWarning::NeverRead(_) => ErrorCode::NeverRead,
+        };
+    }
+    fn notes(&self) -> Vec<String> {
+        return vec![format!("add \"allow {}/\" comment to this line to silence it", self.name())];
+    }
+    fn related_name(&self) -> Option<&str> {
+        return match self {
+            LintWarning::Unreachable(_) => None,
+            LintWarning::NeverPinned(name) => Some(name),
+            LintWarning::NeverRead(name) => Some(name),
+        };
+    }
+}
+
+fn is_allowed(line_text: &str, warning: &LintWarning) -> bool {
+    let Ok(tokens) = lexer::tokenize_line(line_text.trim()) else {
+        return false;
+    };
+    return tokens.iter().any(|token| match token {
+        Token::Comment(text) => {
+            let mut words = text.split_whitespace();
+            words.next() == Some("allow") && words.any(|e| e == warning.name())
+        }
+        _ => false,
+    });
+}
+
+fn index_variable(v: VValue) -> Option<RValue> {
+    return match v {
+        VValue::ArrayElement(ArrayElement {
+            index: IValue::RValue(r),
+            ..
+        }) => Some(r),
+        _ => None,
+    };
+}
+
+fn avalue_variables(a: AValue) -> Vec<RValue> {
+    return match a {
+        AValue::LValue(_) => vec![],
+        AValue::RValue(r) => vec![r],
+        AValue::ArrayElement(_) => index_variable(VValue::try_from(a).unwrap()).into_iter().collect(),
+    };
+}
+
+//variables whose value matters for op, target of math and of "print" is only written
+fn read_variables(op: &Op) -> Vec<RValue> {
+    return match op {
+        Op::Print(a) | Op::PrintASCII(a) => avalue_variables(*a),
+        Op::If(a, b, _) => [avalue_variables(*a), avalue_variables(*b)].concat(),
+        Op::Add(a, b) | Op::Subtract(a, b) => [avalue_variables(*a), index_variable(*b).into_iter().collect()].concat(),
+        Op::Read(v) | Op::ReadASCII(v) => index_variable(*v).into_iter().collect(),
+        _ => vec![],
+    };
+}
+
+fn get_reachable(cfg: &Cfg) -> Vec<bool> {
+    let mut reachable = vec![false; cfg.blocks.len()];
+    reachable[0] = true;
+    let mut work = vec![0];
+    while let Some(id) = work.pop() {
+        for next in cfg.blocks[id].terminator.successors() {
+            if !reachable[next] {
+                reachable[next] = true;
+                work.push(next);
+            }
+        }
+    }
+    return reachable;
+}
+
+//every part of consecutive ops that never run is reported once, at the op that would run first
+fn check_unreachable(repr: &Representation, cfg: &Cfg) -> Vec<(usize, LintWarning)> {
+    let reachable = get_reachable(cfg);
+    let mut warnings = Vec::new();
+    let mut part: Option<(usize, usize)> = None;
+    for i in 0..=repr.ops.len() {
+        if i < repr.ops.len() && !reachable[cfg.block_of[i]] {
+            //"if" only closes the block, on its own it's not code
+            if part.is_none() && !matches!(repr.ops[i].op, Op::If(_, _, _)) {
+                part = Some((i, 0));
+            }
+            if let Some((_, amount)) = &mut part {
+                *amount += 1;
+            }
+        } else if let Some((first, amount)) = part.take() {
+            warnings.push((first, LintWarning::Unreachable(amount)));
+        }
+    }
+    return warnings;
+}
+
+fn check_never_pinned(repr: &Representation) -> Vec<(usize, LintWarning)> {
+    let mut warnings = Vec::new();
+    for (i, op_line) in repr.ops.iter().enumerate() {
+        let Op::Mark(flag) = op_line.op else {
+            continue;
+        };
+        if !repr.ops.iter().any(|e| e.op == Op::Pin(flag)) {
+            warnings.push((i, LintWarning::NeverPinned(repr.get_flag_name(flag))));
+        }
+    }
+    return warnings;
+}
+
+fn check_never_read(repr: &Representation) -> Vec<(usize, LintWarning)> {
+    let mut read = vec![false; repr.variables_names.len()];
+    for op_line in repr.ops.iter() {
+        for id in read_variables(&op_line.op) {
+            read[id.0] = true;
+        }
+        //sheltered "trash" variable needed by every add/sub is in use too
+        if let Op::Add(_, VValue::RValue(id)) | Op::Subtract(_, VValue::RValue(id)) = op_line.op {
+            read[id.0] = true;
+        }
+    }
+    let mut warnings = Vec::new();
+    for (i, op_line) in repr.ops.iter().enumerate() {
+        if let Op::Define(id) = op_line.op {
+            if !read[id.0] {
+                //reported once, at the first definition
+                read[id.0] = true;
+                warnings.push((i, LintWarning::NeverRead(repr.get_variable_name(id))));
+            }
+        }
+    }
+    return warnings;
+}
+
+///dead code and unused names, without the ones allowed by a comment. Empty if the if/fi blocks don't match
+pub fn lint(repr: &Representation) -> Vec<LinedError<LintWarning>> {
+    let Ok(cfg) = Cfg::build(repr) else {
+        return vec![];
+    };
+    let warnings = [
+        check_unreachable(repr, &cfg),
+        check_never_pinned(repr),
+        check_never_read(repr),
+    ];
+    return warnings
+        .into_iter()
+        .flatten()
+        .map(|(i, warning)| (&repr.ops[i], warning))
+        .filter(|(op_line, warning)| !is_allowed(&op_line.line_text, warning))
+        .map(|(op_line, warning)| LinedError::new(op_line.line_num + 1, repr.lines_amount, op_line.line_text.clone(), warning))
+        .collect();
+}
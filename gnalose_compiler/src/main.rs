@@ -1,143 +1,361 @@
-#![allow(dead_code)]
+use std::{
+    env, fs,
+    io::{self, BufWriter, Write},
+    path::Path,
+    process::exit,
+};
 
-use std::{env, fs, process::exit, time::Instant};
-mod compiler;
-mod lexer;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use gnalose_compiler::{
+    integer::{IntWidth, Integers, Overflow},
+    interpreter, native, BadInput, Diagnostic, ExitCode, OptionFlags, Options, Stage,
+};
 
-mod parser;
-mod representation;
-mod string_builder;
-mod test;
-mod token;
-mod utility;
+///"-" stands for stdin/stdout
+const STD_STREAM: &str = "-";
 
-use bitflags::bitflags;
+#[derive(Debug, Parser)]
+#[command(version, about = "Compiler of Gnalose, esoteric language executed from the bottom")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
 
-bitflags! {
-    #[derive(Debug)]
-    pub struct ArgumentFlags: u32{
-        const Verbose= 1<<0;
-        const PrintEverything=1<<1;
-    }
+#[derive(Debug, Subcommand)]
+enum Command {
+    ///compiles to C
+    Compile {
+        #[command(flatten)]
+        common: CommonArgs,
+        ///output file, "-" for stdout [default: output.c or output executable]
+        #[arg(short, long)]
+        output: Option<String>,
+        #[command(flatten)]
+        codegen: CodegenArgs,
+        #[command(flatten)]
+        native: NativeArgs,
+    },
+    ///runs the program directly, without C compiler
+    Run {
+        #[command(flatten)]
+        common: CommonArgs,
+    },
+    ///only reports errors and warnings
+    Check {
+        #[command(flatten)]
+        common: CommonArgs,
+    },
+    ///formats the source file in place, "-" formats stdin to stdout
+    Fmt {
+        ///source file, "-" for stdin
+        input: String,
+        ///only report lines that aren't formatted, nothing is written
+        #[arg(long)]
+        check: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+struct CommonArgs {
+    ///source file, "-" for stdin
+    input: String,
+    ///verbose
+    #[arg(short, long)]
+    verbose: bool,
+    ///print intermediate states
+    #[arg(short = 'p', long)]
+    print_everything: bool,
+    ///fail on leak warnings
+    #[arg(long)]
+    deny_leaks: bool,
+    ///bits of the only type
+    #[arg(long, value_enum, default_value_t = IntWidth::W32)]
+    int_width: IntWidth,
+    ///what happens when result doesn't fit in the type
+    #[arg(long, value_enum, default_value_t = Overflow::Wrap)]
+    overflow: Overflow,
+    ///"read as number to" writes the value as utf-8 encoded code point instead of a single byte
+    #[arg(long)]
+    utf8_output: bool,
+    ///what "print" does when there is no number on the input
+    #[arg(long, value_enum, default_value_t = BadInput::Abort)]
+    on_bad_input: BadInput,
 }
 
-#[derive(Debug, derive_new::new)]
-struct Arguments {
-    input_file_name: String,
-    output_file_name: String,
-    flags: ArgumentFlags,
+#[derive(Debug, Args)]
+struct CodegenArgs {
+    ///don't check array indexes at runtime
+    #[arg(long)]
+    no_bounds_check: bool,
+    ///don't use GCC computed gotos
+    #[arg(long)]
+    portable: bool,
+    ///emit #line directives pointing at the gnalose source
+    #[arg(long)]
+    line_directives: bool,
+    ///fold the global offset of math with literals at compile time
+    #[arg(long)]
+    optimize: bool,
 }
-#[derive(Debug)]
-enum ArgumentParsingError {
-    MissingInputFile,
-    IncorrectArgument(String),
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Emit {
+    C,
+    ///c compiled with system c compiler
+    Exe,
+}
+
+#[derive(Debug, Args)]
+struct NativeArgs {
+    ///what to output
+    #[arg(long, value_enum, default_value_t = Emit::C)]
+    emit: Emit,
+    ///c compiler used for "--emit exe"
+    #[arg(long, env = "CC", default_value = "cc")]
+    cc: String,
+    ///optimization level passed to the c compiler
+    #[arg(short = 'O', long, default_value = "2", value_parser = ["0", "1", "2", "3", "s"])]
+    opt_level: String,
 }
-impl ArgumentParsingError {
-    pub fn get_descr(&self) -> String {
-        return match self {
-            Self::MissingInputFile => "No file name provided".to_owned(),
-            Self::IncorrectArgument(reason) => "Argument couldn't be parsed: ".to_owned() + reason,
+
+impl CommonArgs {
+    fn to_options(&self, codegen: Option<&CodegenArgs>) -> Options {
+        let mut flags = OptionFlags::empty();
+        flags.set(OptionFlags::Verbose, self.verbose);
+        flags.set(OptionFlags::PrintEverything, self.print_everything);
+        flags.set(OptionFlags::DenyLeaks, self.deny_leaks);
+        flags.set(OptionFlags::Utf8Output, self.utf8_output);
+        if let Some(codegen) = codegen {
+            flags.set(OptionFlags::NoBoundsCheck, codegen.no_bounds_check);
+            flags.set(OptionFlags::Portable, codegen.portable);
+            flags.set(OptionFlags::LineDirectives, codegen.line_directives);
+            flags.set(OptionFlags::Optimize, codegen.optimize);
+        }
+        return Options {
+            file_name: Some(get_display_name(&self.input).to_owned()),
+            integers: Integers::new(self.int_width, self.overflow),
+            on_bad_input: self.on_bad_input,
+            ..Options::new(flags)
         };
     }
 }
 
 fn main() {
-    let envs: Vec<String> = env::args().collect();
+    // the file alone still means "compile", the way it worked before subcommands
+    let mut envs: Vec<String> = env::args().collect();
+    let subcommands = ["compile", "run", "check", "fmt", "help"];
+    if envs
+        .get(1)
+        .is_some_and(|e| !subcommands.contains(&e.as_str()) && (e == STD_STREAM || !e.starts_with('-')))
+    {
+        envs.insert(1, "compile".to_owned());
+    }
 
-    let arguments = parse_arguments(&envs[1..]);
-    if let Err(err) = arguments {
-        println!("err while parsing arguments: {}", err.get_descr());
-        return;
+    let cli = match Cli::try_parse_from(envs) {
+        Ok(cli) => cli,
+        Err(err) => {
+            let _ = err.print();
+            exit(if err.use_stderr() {
+                ExitCode::Usage.code()
+            } else {
+                ExitCode::Success.code()
+            });
+        }
+    };
+
+    match cli.command {
+        Command::Compile {
+            common,
+            output,
+            codegen,
+            native,
+        } => {
+            let code = read_source(&common.input);
+            let options = common.to_options(Some(&codegen));
+            let result = compile(&code, &options);
+            if native.emit == Emit::Exe {
+                let output = output.unwrap_or(if cfg!(windows) { "output.exe" } else { "output" }.to_owned());
+                build_executable(&result, &code, &options, &output, &native);
+                return;
+            }
+            let output = output.unwrap_or("output.c".to_owned());
+            if let Err(write_err) = write_output(&output, &result) {
+                eprintln!("error while writing to file:{}", write_err);
+                exit(ExitCode::Io.code());
+            }
+        }
+        Command::Run { common } => {
+            let code = read_source(&common.input);
+            run_gnalose_with_args(&code, &common.to_options(None));
+        }
+        Command::Check { common } => {
+            let code = read_source(&common.input);
+            compile(&code, &common.to_options(None));
+        }
+        Command::Fmt { input, check } => {
+            let code = read_source(&input);
+            //formatting doesn't depend on the width, so literals only have to fit in the widest one
+            let options = Options {
+                file_name: Some(get_display_name(&input).to_owned()),
+                integers: Integers::new(IntWidth::W64, Overflow::default()),
+                ..Options::default()
+            };
+            format(&code, &options, &input, check);
+        }
     }
-    let arguments = arguments.unwrap();
+}
 
-    let code = fs::read_to_string(arguments.input_file_name.trim());
+fn get_display_name(input: &str) -> &str {
+    return if input == STD_STREAM { "<stdin>" } else { input };
+}
 
+fn read_source(input: &str) -> String {
+    let code = if input == STD_STREAM {
+        io::read_to_string(io::stdin())
+    } else {
+        fs::read_to_string(input)
+    };
     if let Err(err) = code {
-        println!("err while reading from file: {}", err);
-        exit(1);
+        eprintln!("err while reading from {}: {}", get_display_name(input), err);
+        exit(ExitCode::Io.code());
     }
-    let code = code.unwrap();
+    return code.unwrap();
+}
 
-    let result = compile_gnalose_to_c_with_args(code.as_str(), &arguments, |f| println!("{}", f));
-    match result {
-        Err(err) => {
-            println!("err:{}", err);
-            exit(1);
-        }
-        Ok(v) => {
-            let res = fs::write(arguments.output_file_name, v);
-            if let Err(write_err) = res {
-                println!("error while writing to file:{}", write_err);
-            }
-        }
+fn write_output(output: &str, content: &str) -> io::Result<()> {
+    if output == STD_STREAM {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(content.as_bytes())?;
+        return stdout.flush();
     }
+    return fs::write(output, content);
 }
 
-fn parse_arguments<'a>(s: &[String]) -> Result<Arguments, ArgumentParsingError> {
-    if s.len() == 0 {
-        return Err(ArgumentParsingError::MissingInputFile);
+fn build_executable(c_code: &str, code: &str, options: &Options, output: &str, native: &NativeArgs) {
+    if output == STD_STREAM {
+        eprintln!("executable can't be written to stdout");
+        exit(ExitCode::Usage.code());
     }
-    let mut arguments = ArgumentFlags::empty();
-    let mut output = "output.c".to_owned();
-    let mut i = 1;
-    while i < s.len() {
-        if s[i] == "-v" {
-            arguments |= ArgumentFlags::Verbose
-        }
-        if s[i] == "-p" {
-            arguments |= ArgumentFlags::PrintEverything
+    let source_file = options.file_name.as_deref().unwrap_or_default();
+    match native::build_executable(c_code, code, source_file, Path::new(output), &native.cc, &native.opt_level) {
+        Ok(warnings) => eprint!("{}", warnings),
+        Err(native::NativeError::Io(err)) => {
+            eprintln!("error while writing temporary c file:{}", err);
+            exit(ExitCode::Io.code());
         }
-        if s[i] == "-o" {
-            output = s
-                .get(i + 1)
-                .ok_or(ArgumentParsingError::IncorrectArgument(
-                    "-o should be followed with output file name".to_owned(),
-                ))?
-                .clone();
-
-            i += 1;
+        Err(err) => {
+            eprintln!("{}", err);
+            exit(ExitCode::NativeBuild.code());
         }
-        i += 1;
     }
-    return Ok(Arguments::new(s[0].trim().to_owned(), output, arguments));
 }
 
-fn compile_gnalose_to_c_with_args<F>(code: &str, arg: &Arguments, out_func: F) -> Result<String, String>
-where
-    F: Fn(&str),
-{
-    let is_verbose = arg.flags.contains(ArgumentFlags::Verbose);
-    let is_print_everything = arg.flags.contains(ArgumentFlags::PrintEverything);
-
-    let bef = Instant::now();
-    let tokens = lexer::tokenize(code).map_err(|er| format!(" [Lexer] {}", er))?;
+//exits on errors, everything besides the output goes to stderr so it can be piped
+fn compile(code: &str, options: &Options) -> String {
+    let result = gnalose_compiler::compile_gnalose_to_c_with_args(code, options, |f| eprintln!("{}", f));
+    match result {
+        Err(errors) => {
+            let exit_code = get_exit_code(&errors);
+            print_diagnostics(errors, |f| eprintln!("{}", f));
+            exit(exit_code.code());
+        }
+        Ok(v) => return v,
+    }
+}
 
-    if is_verbose {
-        out_func(format!("TOKENIZATION DONE in {} s", bef.elapsed().as_secs_f32()).as_str());
+fn format(code: &str, options: &Options, input: &str, check: bool) {
+    let formatted = match gnalose_compiler::format_gnalose_with_args(code, options) {
+        Ok(v) => v,
+        Err(errors) => {
+            let exit_code = get_exit_code(&errors);
+            print_diagnostics(errors, |f| eprintln!("{}", f));
+            exit(exit_code.code());
+        }
+    };
+    if check {
+        if formatted != code {
+            print_format_diff(code, &formatted, get_display_name(input));
+            exit(ExitCode::Unformatted.code());
+        }
+        return;
+    }
+    if formatted == code && input != STD_STREAM {
+        return;
     }
-    if is_print_everything {
-        out_func(format!("Tokenization Output:\n.{}", token::format_token_collection(tokens.as_slice())).as_str());
+    if let Err(write_err) = write_output(input, &formatted) {
+        eprintln!("error while writing to file:{}", write_err);
+        exit(ExitCode::Io.code());
     }
+}
 
-    let bef = Instant::now();
-    let result = parser::parse_to_repr(&tokens).map_err(|err| format!(" [Parser] {}", err))?;
+//lines are compared one by one, formatting never adds or moves them, only removes empty ones at the end
+fn print_format_diff(code: &str, formatted: &str, file: &str) {
+    let old: Vec<&str> = code.strip_prefix('\u{feff}').unwrap_or(code).lines().collect();
+    let new: Vec<&str> = formatted.strip_prefix('\u{feff}').unwrap_or(formatted).lines().collect();
+    let mut reported = false;
+    for (i, old_line) in old.iter().enumerate() {
+        let new_line = new.get(i).copied().unwrap_or_default();
+        if *old_line != new_line {
+            eprintln!("{}:{}: not formatted\n-{}\n+{}", file, i + 1, old_line, new_line);
+            reported = true;
+        }
+    }
+    if !reported {
+        eprintln!(
+            "{}: empty lines at the end, line endings or the last newline not formatted",
+            file
+        );
+    }
+}
 
-    if is_verbose {
-        out_func(format!("PARSING DONE in {} s", bef.elapsed().as_secs_f32()).as_str());
+fn run_gnalose_with_args(code: &str, options: &Options) {
+    // diagnostics go to stderr so they don't mix with the program output
+    let out_func = |f: &str| eprintln!("{}", f);
+    let result = gnalose_compiler::parse_gnalose_with_args(code, options, &out_func)
+        .and_then(|repr| gnalose_compiler::analyze_with_args(&repr, options, &out_func).map(|_| repr));
+    if let Err(errors) = result {
+        let exit_code = get_exit_code(&errors);
+        print_diagnostics(errors, |f| eprintln!("{}", f));
+        exit(exit_code.code());
     }
-    if is_print_everything {
-        out_func(format!("Parsing Output:\n{}", parser::format_representation(&result)).as_str());
+    let repr = result.unwrap();
+
+    let mut output = BufWriter::new(io::stdout().lock());
+    match interpreter::run_with_args(&repr, options, &mut io::stdin().lock(), &mut output) {
+        Ok(()) => {}
+        Err(interpreter::RunError::Runtime(err)) => exit(err.exit_code().code()),
+        Err(interpreter::RunError::Structure(errors)) => {
+            let errors = Diagnostic::from_lined_all(Stage::Compiler, errors);
+            let file = options.file_name.as_deref().unwrap_or_default();
+            let errors = errors.into_iter().map(|e| e.with_file(file)).collect();
+            print_diagnostics(errors, |f| eprintln!("{}", f));
+            exit(ExitCode::Compile.code());
+        }
+        Err(err) => {
+            eprintln!("err:{}", err);
+            exit(ExitCode::Io.code());
+        }
     }
+}
 
-    let bef = Instant::now();
-    let result = compiler::compile(&result).map_err(|err| format!(" [Final Compiler]{}", err))?;
+//errors can come from several stages, the earliest one decides
+fn get_exit_code(errors: &[Diagnostic]) -> ExitCode {
+    return errors
+        .iter()
+        .map(|e| ExitCode::from(e.stage))
+        .min_by_key(|e| e.code())
+        .unwrap_or(ExitCode::Compile);
+}
 
-    if is_verbose {
-        out_func(format!("FINAL COMPILATION STEP DONE IN {} S\n", bef.elapsed().as_secs_f32()).as_str());
+fn print_diagnostics<F>(errors: Vec<Diagnostic>, out_func: F)
+where
+    F: Fn(&str),
+{
+    let count = errors.len();
+    for err in errors {
+        out_func(format!("{}\n", err).as_str());
     }
-    if is_print_everything {
-        out_func(format!("final compilation output:\n{}", result.as_str()).as_str());
+    if count > 1 {
+        out_func(format!("aborting due to {} errors", count).as_str());
     }
-    return Ok(result);
 }
@@ -0,0 +1,157 @@
+//building executable with system c compiler, its messages are mapped back to the gnalose source
+
+use std::{
+    collections::HashMap,
+    env,
+    fmt::{self, Display},
+    fs::{self, DirBuilder, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{self, Command},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug)]
+pub enum NativeError {
+    Io(io::Error),
+    CompilerNotFound(String, io::Error),
+    CompilationFailed(String),
+}
+
+impl Display for NativeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NativeError::Io(err) => write!(f, "{}", err),
+            NativeError::CompilerNotFound(cc, err) => write!(f, "c compiler \"{}\" couldn't be started: {}", cc, err),
+            NativeError::CompilationFailed(messages) => write!(f, "{}c compiler failed", messages),
+        }
+    }
+}
+
+impl From<io::Error> for NativeError {
+    fn from(err: io::Error) -> Self {
+        return NativeError::Io(err);
+    }
+}
+
+///every op ends with "//line_text" comment (see compiler::line_comment), this finds which of the source lines it is
+//ops are emitted in execution order, so k-th c line with given text is the k-th source line with it counted from the bottom
+fn get_source_lines(c_code: &str, source: &str) -> Vec<Option<usize>> {
+    let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+    let lines_amount = source.lines().count();
+    let mut from_bottom: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, line) in source.lines().rev().enumerate() {
+        from_bottom.entry(line).or_default().push(i);
+    }
+
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    let mut result = Vec::new();
+    for c_line in c_code.lines() {
+        let text = c_line
+            .match_indices("//")
+            .map(|(i, _)| &c_line[i + 2..])
+            .map(|e| match e.strip_suffix("//") {
+                Some(t) if t.trim_end().ends_with('\\') => t,
+                _ => e,
+            })
+            .find(|e| from_bottom.contains_key(e));
+
+        let line = text.and_then(|text| {
+            let count = seen.entry(text).or_insert(0);
+            *count += 1;
+            return from_bottom[text].get(*count - 1).map(|i| lines_amount - i);
+        });
+        result.push(line);
+    }
+    return result;
+}
+
+///rewrites "c_file:LINE:" at the start of c compiler messages into "source_file:LINE:" of the gnalose line it was generated from
+pub fn map_c_messages(messages: &str, c_code: &str, c_file: &str, source: &str, source_file: &str) -> String {
+    let source_lines = get_source_lines(c_code, source);
+    let prefix = format!("{}:", c_file);
+    let mut result = String::new();
+
+    for message in messages.lines() {
+        let mapped = message.strip_prefix(prefix.as_str()).and_then(|rest| {
+            let (line, rest) = rest.split_once(':')?;
+            let source_line = (*source_lines.get(line.parse::<usize>().ok()?.checked_sub(1)?)?)?;
+            //column is about the c code, so it's dropped
+            let rest = match rest.split_once(':') {
+                Some((column, rest)) if column.parse::<usize>().is_ok() => rest,
+                _ => rest,
+            };
+            return Some(format!("{}:{}:{}", source_file, source_line, rest));
+        });
+        result += mapped.as_deref().unwrap_or(message);
+        result += "\n";
+    }
+    return result;
+}
+
+//new directory only the current user can access, so nobody can plant the c file (or a symlink) in advance
+fn create_private_dir() -> io::Result<PathBuf> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|e| e.subsec_nanos())
+        .unwrap_or(0);
+    let mut builder = DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    for attempt in 0u32.. {
+        let dir = env::temp_dir().join(format!("gnalose_{}_{}_{}", process::id(), nanos, attempt));
+        match builder.create(&dir) {
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            result => return result.map(|_| dir),
+        }
+    }
+    unreachable!();
+}
+
+fn write_c_file(dir: &Path, c_code: &str) -> io::Result<PathBuf> {
+    let c_path = dir.join("output.c");
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&c_path)?
+        .write_all(c_code.as_bytes())?;
+    return Ok(c_path);
+}
+
+///compiles c code with given c compiler into output executable, on success returns its (mapped) warnings
+///cc can have leading arguments, like "ccache cc" or "gcc -m32"
+pub fn build_executable(
+    c_code: &str,
+    source: &str,
+    source_file: &str,
+    output: &Path,
+    cc: &str,
+    opt_level: &str,
+) -> Result<String, NativeError> {
+    let dir = create_private_dir()?;
+    let c_path = match write_c_file(&dir, c_code) {
+        Ok(c_path) => c_path,
+        Err(err) => {
+            let _ = fs::remove_dir_all(&dir);
+            return Err(err.into());
+        }
+    };
+    let mut cc_args = cc.split_whitespace();
+    let result = Command::new(cc_args.next().unwrap_or_default())
+        .args(cc_args)
+        .arg(&c_path)
+        .arg(format!("-O{}", opt_level))
+        .arg("-o")
+        .arg(output)
+        .output();
+    //leftover temporary file doesn't make the build fail
+    let _ = fs::remove_dir_all(&dir);
+
+    let result = result.map_err(|err| NativeError::CompilerNotFound(cc.to_owned(), err))?;
+    let messages = String::from_utf8_lossy(&result.stderr);
+    let messages = map_c_messages(&messages, c_code, &c_path.to_string_lossy(), source, source_file);
+    if !result.status.success() {
+        return Err(NativeError::CompilationFailed(messages));
+    }
+    return Ok(messages);
+}
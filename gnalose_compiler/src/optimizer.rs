@@ -0,0 +1,115 @@
+//folds the global offset of "add"/"sub" with a literal at compile time
+//inside basic blocks the change of global and of the excluded variable is only remembered,
+//reads are corrected with it, and it is written to the memory once at the end of the block
+
+use crate::{
+    cfg::{Cfg, Terminator},
+    representation::*,
+};
+
+///offsets not yet written to the memory: real global is global+self.global, real raw value is raw-variable(id)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pending {
+    pub global: i64,
+    ///sorted by id, without zeros
+    pub variables: Vec<(RValue, i64)>,
+}
+
+impl Pending {
+    pub fn is_empty(&self) -> bool {
+        return self.global == 0 && self.variables.is_empty();
+    }
+
+    pub fn variable(&self, id: RValue) -> i64 {
+        return self.variables.iter().find(|e| e.0 == id).map(|e| e.1).unwrap_or(0);
+    }
+
+    ///what has to be added to get() of the variable to get its real value
+    pub fn value_offset(&self, id: RValue) -> i64 {
+        return self.global.wrapping_sub(self.variable(id));
+    }
+
+    fn add_variable(&mut self, id: RValue, value: i64) {
+        let value = self.variable(id).wrapping_add(value);
+        self.forget_variable(id);
+        if value != 0 {
+            self.variables.push((id, value));
+            self.variables.sort_by_key(|e| e.0 .0);
+        }
+    }
+
+    fn forget_variable(&mut self, id: RValue) {
+        self.variables.retain(|e| e.0 != id);
+    }
+}
+
+///result of folding, indexed the same as Representation::ops
+#[derive(Debug, Clone, Default)]
+pub struct Folding {
+    ///written to the memory (and cleared) before the op
+    pub flush_before: Vec<Pending>,
+    ///in effect while the op runs, after the flush
+    pub pending: Vec<Pending>,
+}
+
+impl Folding {
+    ///nothing folded, code is compiled as it is
+    pub fn none(ops_amount: usize) -> Folding {
+        return Folding {
+            flush_before: vec![Pending::default(); ops_amount],
+            pending: vec![Pending::default(); ops_amount],
+        };
+    }
+}
+
+pub fn fold_offsets(repr: &Representation, cfg: &Cfg) -> Folding {
+    let mut folding = Folding::none(repr.ops.len());
+    //offsets are kept only inside a basic block, every block starts with nothing pending
+    let mut incoming = Pending::default();
+
+    for block in cfg.blocks.iter() {
+        let mut current = Pending::default();
+        for i in block.ops.clone() {
+            if i == block.ops.start {
+                folding.flush_before[i] = std::mem::take(&mut incoming);
+            }
+            //fi and halt end the block, "if" (which does nothing) ends the block inside of "fi"
+            let ends_block = i + 1 == block.ops.end && matches!(repr.ops[i].op, Op::Fi | Op::If(_, _, _) | Op::Goto);
+            if ends_block && !current.is_empty() {
+                folding.flush_before[i] = std::mem::take(&mut current);
+            }
+            folding.pending[i] = current.clone();
+
+            match repr.ops[i].op {
+                Op::Add(AValue::LValue(value), b) | Op::Subtract(AValue::LValue(value), b) => {
+                    let value = if let Op::Add(_, _) = repr.ops[i].op {
+                        value
+                    } else {
+                        value.wrapping_neg()
+                    };
+                    current.global = current.global.wrapping_add(value);
+                    if let VValue::RValue(id) = b {
+                        current.add_variable(id, value);
+                    }
+                }
+                //raw value is set from scratch
+                Op::Define(id) | Op::Undefine(id) | Op::Read(VValue::RValue(id)) | Op::ReadASCII(VValue::RValue(id)) => {
+                    current.forget_variable(id)
+                }
+                _ => {}
+            }
+        }
+        //falling into the next block, flushed before its first op (label of "mark")
+        if let Terminator::Jump(next) = block.terminator {
+            if next != cfg.exit() {
+                incoming = current;
+            }
+        }
+    }
+    return folding;
+}
+
+///change of global by literal math is fully done by the pending offsets, so is the change of a variable
+pub fn is_folded(op: &Op) -> bool {
+    return matches!(op, Op::Add(AValue::LValue(_), _) | Op::Subtract(AValue::LValue(_), _));
+}
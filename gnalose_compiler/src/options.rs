@@ -0,0 +1,42 @@
+use bitflags::bitflags;
+
+use crate::integer::Integers;
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct OptionFlags: u32{
+        const Verbose= 1<<0;
+        const PrintEverything=1<<1;
+        const NoBoundsCheck=1<<2;
+        const DenyLeaks=1<<3;
+        const Portable=1<<4;
+        const LineDirectives=1<<5;
+        const Optimize=1<<6;
+        const Utf8Output=1<<7;
+    }
+}
+
+///what "print" does when there is no number (or no byte) on the input
+#[derive(Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum)]
+pub enum BadInput {
+    ///program aborts
+    #[default]
+    Abort,
+    ///value becomes 0
+    Zero,
+    ///rest of the line is skipped and it's read again, aborts at the end of the input
+    Retry,
+}
+
+#[derive(Debug, Clone, Default, derive_new::new)]
+pub struct Options {
+    pub flags: OptionFlags,
+    ///shown in diagnostics
+    #[new(default)]
+    pub file_name: Option<String>,
+    ///width and overflow of the only type, used from lexing to codegen
+    #[new(default)]
+    pub integers: Integers,
+    #[new(default)]
+    pub on_bad_input: BadInput,
+}
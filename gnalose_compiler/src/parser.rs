@@ -2,7 +2,7 @@
 
 use derive_new::new;
 
-use crate::{representation::*, string_builder, token::*, utility::*};
+use crate::{integer::Integers, representation::*, string_builder, token::*, utility::*};
 
 #[derive(new, Default)]
 struct ParsingTempState {
@@ -24,7 +24,7 @@ impl ParsingTempState {
         return Some(FlagRef(self.flags.iter().find_i(t)?));
     }
     pub fn get_array(&mut self, t: &str) -> Option<ArrayRef> {
-        return self.array_names.iter().position(|el| el.0 == t).map(|e| ArrayRef(e));
+        return self.array_names.iter().position(|el| el.0 == t).map(ArrayRef);
     }
 
     pub fn get_or_insert_var(&mut self, t: &str) -> RValue {
@@ -45,8 +45,8 @@ impl ParsingTempState {
 //micro problems returns none both in case of "finish" and error
 //it shouldn't matter, in case of error it will probably cause "wrong structure" anyway
 ///asummes no comment
-fn tokens_to_higher_tokens_next<'a>(tokens: &'a [Token]) -> Option<(&'a [Token], HigherToken)> {
-    if tokens.len() == 0 {
+fn tokens_to_higher_tokens_next(tokens: &[Token]) -> Option<(&[Token], HigherToken)> {
+    if tokens.is_empty() {
         return None;
     }
     return match &tokens[0] {
@@ -70,6 +70,7 @@ fn tokens_to_higher_tokens_next<'a>(tokens: &'a [Token]) -> Option<(&'a [Token],
             Some((&tokens[1..], (HigherToken::Name(name.clone()))))
         }
         Token::Literal(l) => Some((&tokens[1..], (HigherToken::Literal(*l)))),
+        Token::Text(text) => Some((&tokens[1..], (HigherToken::Text(text.clone())))),
         Token::Comment(_) => None,
         Token::ArrayBracket(_) => None,
     };
@@ -90,6 +91,7 @@ fn tokens_to_higher_tokens(tokens: &[Token]) -> Vec<HigherToken> {
 fn token_to_avalue(data: &mut ParsingTempState, l: &HigherToken) -> Result<AValue, OpParsingError> {
     match l {
         HigherToken::Literal(literal) => Ok(AValue::LValue(*literal)),
+        HigherToken::Text(_) => Err(OpParsingError::InvalidStructure),
 
         HigherToken::Name(name) => Ok(AValue::RValue(
             data.get_var(name.as_str())
@@ -99,7 +101,7 @@ fn token_to_avalue(data: &mut ParsingTempState, l: &HigherToken) -> Result<AValu
         HigherToken::Array(name, index) => Ok(AValue::ArrayElement(ArrayElement::new(
             data.get_array(name.as_str())
                 .ok_or(OpParsingError::NotDefinedVariable(name.to_owned(), NameType::Array))?,
-            upgrade_name_or_number_to_ivalue(data, &index),
+            upgrade_name_or_number_to_ivalue(data, index),
         ))),
     }
 }
@@ -121,7 +123,7 @@ fn token_force_to_flag(data: &mut ParsingTempState, l: &HigherToken) -> Result<F
 
 fn try_name_as_array_def(data: &mut ParsingTempState, i: &HigherToken) -> Option<ArrayRef> {
     if let HigherToken::Name(name) = i {
-        return Some(data.get_array(name.as_str())?);
+        return data.get_array(name.as_str());
     }
     return None;
 }
@@ -135,7 +137,7 @@ fn try_token_as_array_def(data: &mut ParsingTempState, l: &HigherToken) -> Optio
 fn match_format<const N: usize, const N2: usize>(
     name_parts: [(usize, &str); N],
     allowed_kinds: [(usize, AllowedKind); N2],
-    tokens: &Vec<HigherToken>,
+    tokens: &[HigherToken],
 ) -> bool {
     let size = name_parts
         .iter()
@@ -164,7 +166,7 @@ fn match_format<const N: usize, const N2: usize>(
 }
 
 //assumes no comment
-fn parse_line_internal(data: &mut ParsingTempState, tokens: &Vec<Token>) -> Result<Op, OpParsingError> {
+fn parse_line_internal(data: &mut ParsingTempState, tokens: &[Token]) -> Result<Op, OpParsingError> {
     let tokens = tokens_to_higher_tokens(&tokens[0..]);
 
     let get_rval = |i: usize, data: &mut ParsingTempState| token_force_to_rval(data, &tokens[i]);
@@ -176,10 +178,20 @@ fn parse_line_internal(data: &mut ParsingTempState, tokens: &Vec<Token>) -> Resu
     //define/undefine for arrays HAS to be before normal define/undefine
 
     if match_format([(0, "undefine"), (1, "single")], [(2, AllowedKind::ArrayRef)], &tokens) {
+        if let HigherToken::Array(name, NameOrNumber::Number(size)) = &tokens[2] {
+            if *size < 0 {
+                //still defined, so its uses aren't reported too
+                data.get_or_insert_array(name.as_str(), 0);
+                return Err(OpParsingError::NegativeArraySize(name.clone(), *size));
+            }
+        }
         return Ok(Op::DefineArray(try_token_as_array_def(data, &tokens[2]).unwrap()));
     }
     if match_format([(0, "define"), (1, "single")], [(2, AllowedKind::Name)], &tokens) {
-        return Ok(Op::UndefineArray(try_name_as_array_def(data, &tokens[2]).unwrap()));
+        let name = tokens[2].try_to_name_ref().unwrap().to_owned();
+        return Ok(Op::UndefineArray(
+            try_name_as_array_def(data, &tokens[2]).ok_or(OpParsingError::NotDefinedVariable(name, NameType::Array))?,
+        ));
     }
 
     if match_format([(0, "undefine")], [(1, AllowedKind::Name)], &tokens) {
@@ -191,6 +203,9 @@ fn parse_line_internal(data: &mut ParsingTempState, tokens: &Vec<Token>) -> Resu
     if match_format([(0, "print")], [(1, AllowedKind::VValue)], &tokens) {
         return Ok(Op::Read(get_vval(1, data)?));
     }
+    if match_format([(0, "print"), (1, "as"), (2, "number")], [(3, AllowedKind::VValue)], &tokens) {
+        return Ok(Op::ReadASCII(get_vval(3, data)?));
+    }
     if match_format([(0, "read"), (1, "to")], [(2, AllowedKind::VValue)], &tokens) {
         return Ok(Op::Print(get_aval(2, data)?));
     }
@@ -201,6 +216,16 @@ fn parse_line_internal(data: &mut ParsingTempState, tokens: &Vec<Token>) -> Resu
     ) {
         return Ok(Op::PrintASCII(get_aval(4, data)?));
     }
+    if match_format(
+        [(0, "read"), (1, "as"), (2, "text"), (3, "to")],
+        [(4, AllowedKind::Text)],
+        &tokens,
+    ) {
+        let HigherToken::Text(text) = &tokens[4] else {
+            unreachable!("checked by match_format");
+        };
+        return Ok(Op::PrintText(text.clone()));
+    }
 
     if match_format(
         [(0, "add"), (2, "to")],
@@ -295,7 +320,7 @@ fn parse_line(state: &mut ParsingTempState, token_line: &TokenLine, line: usize)
         .map(|e| (*e).clone())
         .collect();
 
-    if tokens.len() == 0 {
+    if tokens.is_empty() {
         return Ok(None);
     }
 
@@ -304,23 +329,62 @@ fn parse_line(state: &mut ParsingTempState, token_line: &TokenLine, line: usize)
     return Ok(Some(OpLine::new(internal, line, token_line.line.clone())));
 }
 
-pub fn parse_to_repr(tokens: &Vec<TokenLine>) -> Result<Representation, LinedError<OpParsingError>> {
+///parse_to_repr_with_integers for the default integers
+pub fn parse_to_repr(tokens: &[TokenLine]) -> Result<Representation, Vec<LinedError<OpParsingError>>> {
+    return parse_to_repr_with_integers(tokens, Integers::default());
+}
+
+///lines are independent, so parsing goes on after an error and all of them are reported
+pub fn parse_to_repr_with_integers(
+    tokens: &[TokenLine],
+    integers: Integers,
+) -> Result<Representation, Vec<LinedError<OpParsingError>>> {
+    let (repr, errors) = parse_to_repr_partial(tokens, integers);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    return Ok(repr);
+}
+
+//"if"/"fi" of a line that didn't parse (or lex), so its pair isn't reported as unmatched too
+fn block_placeholder(line_text: &str) -> Option<Op> {
+    let code = line_text.rsplit('/').next().unwrap_or_default();
+    return match code.split_whitespace().next() {
+        Some("if") => Some(Op::If(AValue::LValue(0), AValue::LValue(0), ConditionType::Equal)),
+        Some("fi") => Some(Op::Fi),
+        _ => None,
+    };
+}
+
+///what parsed and errors of the rest, only for reporting. Failed "if"/"fi" still take part in block matching
+pub fn parse_to_repr_partial(tokens: &[TokenLine], integers: Integers) -> (Representation, Vec<LinedError<OpParsingError>>) {
     let mut ops = Vec::new();
+    let mut errors = Vec::new();
     let mut temp = ParsingTempState::default();
 
     for i in 0..tokens.len() {
         let token_line = &tokens[i];
-        if token_line.tokens.len() == 0 {
+        //blank or failed to lex
+        if token_line.tokens.is_empty() {
+            if let Some(op) = block_placeholder(&token_line.line) {
+                ops.push(OpLine::new(op, i, token_line.line.clone()));
+            }
             continue;
         }
-        let op = parse_line(&mut temp, token_line, i)
-            .map_err(|er| LinedError::new(i + 1, tokens.len(), token_line.line.to_owned(), er))?;
-
-        if let Some(v) = op {
-            ops.push(v);
+        match parse_line(&mut temp, token_line, i) {
+            Ok(Some(v)) => ops.push(v),
+            Ok(None) => {}
+            Err(er) => {
+                if let Some(op) = block_placeholder(&token_line.line) {
+                    ops.push(OpLine::new(op, i, token_line.line.clone()));
+                }
+                errors.push(LinedError::new(i + 1, tokens.len(), token_line.line.to_owned(), er));
+            }
         }
     }
-    return Ok(Representation::new(temp.variables, temp.array_names, temp.flags, ops));
+    let mut repr = Representation::new(temp.variables, temp.array_names, temp.flags, ops, tokens.len());
+    repr.integers = integers;
+    return (repr, errors);
 }
 
 pub fn format_op_collection(ops: &[OpLine]) -> String {
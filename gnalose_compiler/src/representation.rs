@@ -1,9 +1,10 @@
 //structs represeting state after being parsed
 
+use crate::diagnostic::{DiagnosticSource, ErrorCode};
+use crate::integer::Integers;
 use crate::utility::*;
 use derive_new::*;
 
-use derive_new;
 use std::fmt::{self, Display};
 use subenum::subenum;
 
@@ -27,7 +28,7 @@ pub struct ArrayElement {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AValue {
     #[subenum(IValue)]
-    LValue(i32),
+    LValue(i64),
     #[subenum(VValue, IValue)]
     RValue(RValue),
     #[subenum(VValue)]
@@ -36,9 +37,9 @@ pub enum AValue {
 
 impl IValue {
     pub fn as_avalue(&self) -> AValue {
-        return match self {
-            &IValue::RValue(rf) => AValue::RValue(rf),
-            &IValue::LValue(vl) => AValue::LValue(vl),
+        return match *self {
+            IValue::RValue(rf) => AValue::RValue(rf),
+            IValue::LValue(vl) => AValue::LValue(vl),
         };
     }
 }
@@ -59,8 +60,10 @@ pub enum Op {
     DefineArray(ArrayRef),
     Undefine(RValue),
     Read(VValue),
+    ReadASCII(VValue),
     Print(AValue),
     PrintASCII(AValue),
+    PrintText(String),
     Subtract(AValue, VValue),
     Add(AValue, VValue),
     If(AValue, AValue, ConditionType),
@@ -84,6 +87,10 @@ pub struct Representation {
     pub array_names: Vec<(String, usize)>,
     pub flags_names: Vec<String>,
     pub ops: Vec<OpLine>,
+    pub lines_amount: usize,
+    ///literals were checked against it
+    #[new(default)]
+    pub integers: Integers,
 }
 
 impl Representation {
@@ -106,23 +113,39 @@ impl Representation {
         return self.array_names[id.0].0.clone();
     }
     pub fn get_array_size(&self, id: ArrayRef) -> usize {
-        return self.array_names[id.0].1.clone();
+        return self.array_names[id.0].1;
+    }
+    ///line number counted from the top, the way it's seen in the source file
+    pub fn get_line_from_top(&self, op: &OpLine) -> usize {
+        return self.lines_amount - op.line_num;
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum NameType {
     Variable,
     Array,
     Flag,
 }
 
+impl NameType {
+    ///lowercase, as used in runtime messages
+    pub fn describe(&self) -> &'static str {
+        return match self {
+            NameType::Variable => "variable",
+            NameType::Array => "array",
+            NameType::Flag => "flag",
+        };
+    }
+}
+
 #[derive(Debug)]
 pub enum OpParsingError {
     NameUsedTwice(String, NameType, NameType),
     NotDefinedVariable(String, NameType),
     InvalidStructure,
     DoubleLabel(String),
+    NegativeArraySize(String, i64),
 }
 
 #[derive(Debug)]
@@ -131,19 +154,21 @@ pub enum AllowedKind {
     VValue,
     AValue,
     ArrayRef,
+    Text,
 }
 
 #[derive(Debug, PartialEq)]
 pub enum NameOrNumber {
     String(String),
-    Number(i32),
+    Number(i64),
 }
 
 #[derive(Debug, PartialEq)]
 pub enum HigherToken {
     Name(String),
     Array(String, NameOrNumber),
-    Literal(i32),
+    Literal(i64),
+    Text(String),
 }
 impl Display for OpParsingError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -159,10 +184,45 @@ impl Display for OpParsingError {
             OpParsingError::DoubleLabel(label) => {
                 write!(f, "label \"{}\" was defined twice", label)
             }
+            OpParsingError::NegativeArraySize(name, size) => {
+                write!(f, "array \"{}\" can't have negative size {}", name, size)
+            }
         }
     }
 }
 
+impl DiagnosticSource for OpParsingError {
+    fn code(&self) -> ErrorCode {
+        return match self {
+            OpParsingError::NameUsedTwice(_, _, _) => ErrorCode::NameUsedTwice,
+            OpParsingError::NotDefinedVariable(_, _) => ErrorCode::NotDefinedVariable,
+            OpParsingError::InvalidStructure => ErrorCode::InvalidStructure,
+            OpParsingError::DoubleLabel(_) => ErrorCode::DoubleLabel,
+            OpParsingError::NegativeArraySize(_, _) => ErrorCode::NegativeArraySize,
+        };
+    }
+    fn notes(&self) -> Vec<String> {
+        return match self {
+            OpParsingError::NotDefinedVariable(_, NameType::Flag) => {
+                vec!["code is executed from the bottom, \"unmark\" has to be below the first use of the label".to_owned()]
+            }
+            OpParsingError::NotDefinedVariable(_, _) => {
+                vec!["code is executed from the bottom, \"undefine\" has to be below the first use of the name".to_owned()]
+            }
+            _ => vec![],
+        };
+    }
+    fn related_name(&self) -> Option<&str> {
+        return match self {
+            OpParsingError::NameUsedTwice(name, _, _) => Some(name),
+            OpParsingError::NotDefinedVariable(name, _) => Some(name),
+            OpParsingError::DoubleLabel(name) => Some(name),
+            OpParsingError::NegativeArraySize(name, _) => Some(name),
+            OpParsingError::InvalidStructure => None,
+        };
+    }
+}
+
 impl HigherToken {
     pub fn try_to_name_ref(&self) -> Option<&str> {
         if let HigherToken::Name(name) = self {
@@ -177,8 +237,9 @@ impl AllowedKind {
         match *self {
             Self::Name => matches!(t, HigherToken::Name(_)),
             Self::VValue => matches!(t, HigherToken::Name(_)) || matches!(t, HigherToken::Array(_, _)),
-            Self::AValue => true,
+            Self::AValue => !matches!(t, HigherToken::Text(_)),
             Self::ArrayRef => matches!(t, HigherToken::Array(_, _)),
+            Self::Text => matches!(t, HigherToken::Text(_)),
         }
     }
 }
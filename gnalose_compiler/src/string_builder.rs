@@ -6,7 +6,7 @@ pub fn push(builder: &mut Vec<char>, v: &str) {
 pub fn push_line(builder: &mut Vec<char>, v: &str) {
     push(builder, format!("{}\n", v).as_str());
 }
-pub fn reduce_additive<'a, I, T, F>(slice: I, operation: F) -> String
+pub fn reduce_additive<I, T, F>(slice: I, operation: F) -> String
 where
     I: Iterator<Item = T>,
     F: Fn(T) -> String,
@@ -36,9 +36,6 @@ impl Builder {
     pub fn push(&mut self, t: &str) {
         push(&mut self.0, t);
     }
-    pub fn push_line(&mut self, t: &str) {
-        push_line(&mut self.0, t);
-    }
     pub fn collapse(self) -> String {
         return collapse(self.0);
     }
@@ -1,6 +1,7 @@
 #[cfg(test)]
 mod test {
 
+    use crate::interpreter;
     use crate::lexer::*;
     use crate::parser::*;
     use crate::representation::*;
@@ -50,4 +51,334 @@ mod test {
         assert_eq!(repr.ops[4].op, Add(AValue::LValue(3), VValue::RValue(trash)));
         assert_eq!(repr.ops[5].op, Print(AValue::RValue(a)));
     }
+
+    fn run_code(code: &str, input: &str) -> (String, bool) {
+        let repr = parse_to_repr(&tokenize(code).unwrap()).unwrap();
+        let mut output = Vec::new();
+        let result = interpreter::run(&repr, &mut input.as_bytes(), &mut output);
+        return (String::from_utf8(output).unwrap(), result.is_ok());
+    }
+
+    #[test]
+    fn interpreter_test_a() {
+        let code = r#"
+        define c
+        define b
+        define a
+        read to a
+        sub b from c
+        print b
+        print a
+        undefine c
+        undefine b
+        undefine a
+        "#;
+
+        assert_eq!(run_code(code, "5 7"), ("12\n".to_owned(), true));
+    }
+
+    #[test]
+    fn interpreter_test_leak() {
+        let (output, ok) = run_code("undefine a", "");
+        assert!(!ok);
+        assert!(output.starts_with("ABORTED\nMemory leaked: a."));
+    }
+
+    #[test]
+    fn lexer_spans_test() {
+        let tokens = tokenize("hey/  add 12 to arr[i]").unwrap();
+        assert_eq!(
+            tokens[0].spans,
+            vec![0..3, 6..9, 10..12, 13..15, 16..19, 19..20, 20..21, 21..22]
+        );
+    }
+
+    #[test]
+    fn diagnostic_test() {
+        let err = crate::compile_str("define a\n  read to b\nundefine a", crate::Options::default()).unwrap_err();
+        assert_eq!(err.code, crate::diagnostic::ErrorCode::NotDefinedVariable);
+        assert_eq!(err.get_line_from_top(), 2);
+        assert_eq!(err.span, Some(10..11));
+
+        let rendered = err.with_file("a.gnalose").to_string();
+        assert!(rendered.starts_with("error[G0003]: \"b\" is not defined as Variable at this point\n --> a.gnalose:2:11"));
+        assert!(rendered.contains("2 |   read to b\n  |           ^\n"));
+    }
+
+    #[test]
+    fn all_errors_test() {
+        let code = "define a\nread to b\nfoo bar\nmark x\nundefine a";
+        let errors = crate::compile_gnalose_to_c_with_args(code, &crate::Options::default(), |_| {}).unwrap_err();
+        let lines: Vec<usize> = errors.iter().map(|e| e.get_line_from_top()).collect();
+        assert_eq!(lines, vec![2, 3, 4]);
+
+        let repr = parse_to_repr(&tokenize("fi\nif 1 equal to 2").unwrap()).unwrap();
+        assert_eq!(
+            crate::compiler::compile(&repr, &crate::Options::default()).unwrap_err().len(),
+            2
+        );
+
+        //every stage at once, "if" that didn't lex still matches its "fi"
+        let code = "if 99999999999 equal to 1\nread to nope\nfi\nread to 99999999999\nfi";
+        let errors = crate::compile_gnalose_to_c_with_args(code, &crate::Options::default(), |_| {}).unwrap_err();
+        let found: Vec<(usize, crate::Stage)> = errors.iter().map(|e| (e.get_line_from_top(), e.stage)).collect();
+        assert_eq!(
+            found,
+            vec![
+                (1, crate::Stage::Lexer),
+                (2, crate::Stage::Parser),
+                (4, crate::Stage::Lexer),
+                (5, crate::Stage::Compiler)
+            ]
+        );
+    }
+
+    #[test]
+    fn leak_analysis_test() {
+        let code = "define b\nif b not equal to 0\ndefine a\nfi\nprint b\nundefine b\nundefine a";
+        let repr = parse_to_repr(&tokenize(code).unwrap()).unwrap();
+        let warnings = crate::analysis::check_leaks(&repr);
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(&warnings[0].content, crate::analysis::AnalysisWarning::MayLeak(name, _) if name == "a"));
+        assert_eq!(warnings[0].related_text, "undefine a");
+
+        assert!(crate::compile_str(code, crate::Options::default()).is_ok());
+        let denied = crate::compile_str(code, crate::Options::new(crate::OptionFlags::DenyLeaks)).unwrap_err();
+        assert_eq!(denied.code, crate::diagnostic::ErrorCode::MayLeak);
+
+        let code = "print a\ndefine a\nundefine a";
+        let repr = parse_to_repr(&tokenize(code).unwrap()).unwrap();
+        let warnings = crate::analysis::check_leaks(&repr);
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(&warnings[0].content, crate::analysis::AnalysisWarning::MayBeUndefined(name, _) if name == "a"));
+
+        let repr = parse_to_repr(&tokenize("define a\nprint a\nundefine a").unwrap()).unwrap();
+        assert!(crate::analysis::check_leaks(&repr).is_empty());
+    }
+
+    #[test]
+    fn c_messages_mapping_test() {
+        let code = "define a\nread to a\nread to a\nundefine a";
+        let c_code = crate::compile_str(code, crate::Options::default()).unwrap();
+        //prints are executed bottom first, so they are in c in that order
+        let print_lines: Vec<usize> = c_code
+            .lines()
+            .enumerate()
+            .filter(|e| e.1.ends_with("//read to a"))
+            .map(|e| e.0 + 1)
+            .collect();
+        assert_eq!(print_lines.len(), 2);
+
+        let messages = format!(
+            "gen.c: In function 'main':\ngen.c:{}:5: error: first\ngen.c:{}:1: warning: second\ngen.c:1:1: note: prelude",
+            print_lines[0], print_lines[1]
+        );
+        let mapped = crate::native::map_c_messages(&messages, &c_code, "gen.c", code, "a.gnalose");
+        assert_eq!(
+            mapped,
+            "gen.c: In function 'main':\na.gnalose:3: error: first\na.gnalose:2: warning: second\ngen.c:1:1: note: prelude\n"
+        );
+    }
+
+    #[test]
+    fn line_directives_test() {
+        let mut options = crate::Options::new(crate::OptionFlags::LineDirectives);
+        options.file_name = Some("a.gnalose".to_owned());
+        let c_code = crate::compile_str("define a\n\nread to a\nundefine a", options).unwrap();
+        assert!(c_code.contains("#line 4 \"a.gnalose\"\nv_a=-global;"));
+        assert!(c_code.contains("#line 3 \"a.gnalose\"\nASSERT_ALIVE"));
+        assert!(!crate::compile_str("define a\nundefine a", crate::Options::default())
+            .unwrap()
+            .contains("#line"));
+    }
+
+    #[test]
+    fn optimizer_test() {
+        //executed: undefine a, undefine b, sub 2 from a, add 3 to b, read to a, mark/halt loop
+        let code =
+            "define b\ndefine a\nmark l\nhalt\nforget l\nread to a\nadd 3 to b\nsub 2 from a\nunmark l\nundefine b\nundefine a";
+        let c_code = crate::compile_str(code, crate::Options::new(crate::OptionFlags::Optimize)).unwrap();
+        assert!(!c_code.contains("value temp"));
+        assert!(c_code.contains("printf(\"%lld\\n\",as_signed((get(v_a)-3)));//read to a"));
+        assert!(c_code.contains("global+=-1;v_a-=+2;v_b-=-3;GOTO("));
+
+        let repr = crate::parse_str(code, crate::Options::default()).unwrap();
+        let folding = crate::optimizer::fold_offsets(&repr, &crate::cfg::Cfg::build(&repr).unwrap());
+        assert!(folding.pending[3].is_empty() && folding.pending.last().unwrap().is_empty());
+        assert_eq!(folding.pending[5].global, -1);
+    }
+
+    #[test]
+    fn cfg_test() {
+        use crate::cfg::{Cfg, Terminator};
+        let code = include_str!("../../examples/1to100.gnalose");
+        let cfg = Cfg::build(&parse_to_repr(&tokenize(code).unwrap()).unwrap()).unwrap();
+        let ranges: Vec<_> = cfg.blocks.iter().map(|e| e.ops.clone()).collect();
+        assert_eq!(ranges, vec![0..2, 2..4, 4..8, 8..9, 9..12, 12..12]);
+        assert_eq!(
+            cfg.blocks[1].terminator,
+            Terminator::Branch {
+                if_op: 8,
+                then: 2,
+                otherwise: 4
+            }
+        );
+        assert_eq!(
+            cfg.blocks[2].terminator,
+            Terminator::Goto {
+                targets: vec![1],
+                may_abort: false
+            }
+        );
+        assert_eq!(cfg.blocks[1].predecessors, vec![0, 2]);
+        assert_eq!(cfg.blocks[cfg.exit()].terminator, Terminator::Exit);
+
+        let cfg = Cfg::build(&parse_to_repr(&tokenize("halt\nforget a\nmark a\nunmark a\nhalt").unwrap()).unwrap()).unwrap();
+        assert_eq!(
+            cfg.blocks[0].terminator,
+            Terminator::Goto {
+                targets: vec![],
+                may_abort: true
+            }
+        );
+    }
+
+    #[test]
+    fn lint_test() {
+        use crate::lint::{lint, LintWarning};
+        let code = "define b\ndefine a\nmark l\nread to a\nhalt\nforget l\nunmark l\nunmark m\nundefine b\nundefine a";
+        let repr = parse_to_repr(&tokenize(code).unwrap()).unwrap();
+        let warnings = lint(&repr);
+        let found: Vec<(&str, usize)> = warnings.iter().map(|e| (e.content.name(), e.line)).collect();
+        assert_eq!(found, vec![("unreachable", 7), ("never-pinned", 3), ("never-read", 2)]);
+        assert!(matches!(&warnings[0].content, LintWarning::Unreachable(4)));
+
+        let code = code
+            .replace("read to a", "allow unreachable/read to a")
+            .replace("unmark m", "x/allow never-pinned never-read/unmark m")
+            .replace("undefine b", "allow never-read/undefine b");
+        assert!(lint(&parse_to_repr(&tokenize(&code).unwrap()).unwrap()).is_empty());
+
+        //only sheltered by add/sub, like "trash" variables
+        assert!(lint(&parse_to_repr(&tokenize("define t\nsub 1 from t\nundefine t").unwrap()).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn formatter_test() {
+        let format = |code: &str| crate::format_gnalose_with_args(code, &crate::Options::default()).unwrap();
+        let code = "define single arr\n  define i\nif  arr [ i ] equal to 00072\n  hey/  read to i\nfi   \n\nundefine i\nundefine single arr [3]\n\n";
+        let expected =
+            "define single arr\ndefine i\nif arr[i] equal to 72\n\they/read to i\nfi\n\nundefine i\nundefine single arr[3]\n";
+        assert_eq!(format(code), expected);
+        assert_eq!(format(expected), expected);
+        assert_eq!(format("\u{feff}if 1 equal to 1\r\nfi"), "\u{feff}if 1 equal to 1\r\nfi\r\n");
+
+        //blocks of div.gnalose are indented by hand
+        let div = include_str!("../../examples/div.gnalose");
+        let lines: Vec<&str> = div.lines().collect();
+        assert_eq!(format(div).lines().take(17).collect::<Vec<&str>>(), lines[..17]);
+
+        assert!(crate::format_gnalose_with_args("read to a", &crate::Options::default()).is_err());
+    }
+
+    #[test]
+    fn cst_test() {
+        let code = "\u{feff}  hey/ you /define a  \r\n\n\tonly comment/\r\nundefine a";
+        let cst = crate::parse_cst_with_args(code, &crate::Options::default()).unwrap();
+        assert_eq!(cst.to_source(), code);
+        assert_eq!(cst.lines.len(), 4);
+        assert_eq!(cst.lines[0].indent, "  ");
+        assert_eq!(cst.lines[0].comments, vec!["hey".to_owned(), " you ".to_owned()]);
+        assert_eq!(cst.lines[0].code, "define a  ");
+        assert_eq!(cst.lines[0].op, Some(Undefine(RValue(0))));
+        assert!(cst.lines[1].is_blank() && cst.lines[1].op.is_none());
+        assert_eq!((cst.lines[2].op.as_ref(), cst.lines[2].line_ending.as_str()), (None, "\r\n"));
+        assert_eq!(cst.lines[3].line_ending, "");
+
+        let dirs = ["../examples", "tests/fixtures"].map(|e| std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(e));
+        for entry in dirs.iter().flat_map(|e| std::fs::read_dir(e).unwrap()) {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|e| e == "gnalose") {
+                let code = std::fs::read_to_string(&path).unwrap();
+                //literals only have to fit in the widest integer, like in fmt
+                let options = crate::Options {
+                    integers: crate::integer::Integers::new(crate::integer::IntWidth::W64, Default::default()),
+                    ..crate::Options::default()
+                };
+                let cst = crate::parse_cst_with_args(&code, &options).unwrap();
+                assert_eq!(cst.to_source(), code, "{}", path.display());
+            }
+        }
+    }
+
+    #[test]
+    fn negative_literal_test() {
+        let tokens = tokenize_with_width("add -5 to a-b [-2147483648]", crate::integer::IntWidth::W32).unwrap()[0]
+            .tokens
+            .clone();
+        assert_eq!(tokens[1], Token::Literal(-5));
+        assert_eq!(tokens[3], Token::Name(String::from("a-b")));
+        assert_eq!(tokens[5], Token::Literal(i32::MIN as i64));
+
+        let err = tokenize_line("add - 5 to a").unwrap_err();
+        assert!(matches!(err.error, LexingErrorKind::LoneMinus));
+        assert!(matches!(
+            tokenize("add -2147483649 to a").unwrap_err()[0].content.error,
+            LexingErrorKind::OutOfRange(_)
+        ));
+
+        let err = crate::compile_str("define single arr\nundefine single arr[-2]", crate::Options::default()).unwrap_err();
+        assert_eq!(err.code, crate::diagnostic::ErrorCode::NegativeArraySize);
+    }
+
+    #[test]
+    fn char_and_text_literal_test() {
+        let tokens = tokenize_line(r#"a/b/read as text to "x/y \"\n" 'a' '\'' 'ż'"#).unwrap();
+        assert_eq!(tokens[1], Token::Comment(String::from("b")));
+        assert_eq!(tokens[6], Token::Text(String::from("x/y \"\n")));
+        assert_eq!(tokens[7], Token::Literal(97));
+        assert_eq!(tokens[8], Token::Literal(39));
+        assert_eq!(tokens[9], Token::Literal('ż' as i64));
+        //quote inside of a comment doesn't start a literal
+        assert_eq!(tokenize_line("it's/fi").unwrap()[1], Token::Name(String::from("fi")));
+
+        let error = |code: &str| tokenize_line(code).unwrap_err().error;
+        assert!(matches!(error("read as text to \"abc"), LexingErrorKind::Unterminated));
+        assert!(matches!(error("sub 'ab' from a"), LexingErrorKind::NotSingleChar));
+        assert!(matches!(error("sub '' from a"), LexingErrorKind::NotSingleChar));
+        assert!(matches!(
+            error(r#"read as text to "\q""#),
+            LexingErrorKind::InvalidEscape('q')
+        ));
+        assert!(matches!(
+            error(r#"read as text to "\0""#),
+            LexingErrorKind::InvalidEscape('0')
+        ));
+
+        let repr = parse_to_repr(&tokenize("read as text to \"hi\"").unwrap()).unwrap();
+        assert_eq!(repr.ops[0].op, PrintText(String::from("hi")));
+        assert!(parse_to_repr(&tokenize("read to \"hi\"").unwrap()).is_err());
+
+        let format = |code: &str| crate::format_gnalose_with_args(code, &crate::Options::default()).unwrap();
+        assert_eq!(
+            format("read  as text to   \"a  b\"\nif  'c'  equal to 99\nfi"),
+            "read as text to \"a  b\"\nif 'c' equal to 99\nfi\n"
+        );
+    }
+
+    #[test]
+    fn integer_width_test() {
+        use crate::integer::{IntWidth, Integers, Overflow};
+        let options = |width: IntWidth, overflow: Overflow| crate::Options {
+            integers: Integers::new(width, overflow),
+            ..crate::Options::default()
+        };
+        //running with different widths is checked by the overflow_* fixtures
+        let code = "sub 9223372036854775807 from a\nundefine a";
+        assert!(crate::parse_str(code, options(IntWidth::W64, Overflow::Wrap)).is_ok());
+        let err = crate::parse_str(code, options(IntWidth::W32, Overflow::Wrap)).unwrap_err();
+        assert_eq!(err.code, crate::diagnostic::ErrorCode::InvalidLiteral);
+        assert!(err.to_string().contains("doesn't fit in 32-bit integer"));
+        assert!(crate::parse_str("sub 'ż' from a\nundefine a", options(IntWidth::W8, Overflow::Wrap)).is_err());
+    }
 }
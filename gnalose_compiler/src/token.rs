@@ -1,6 +1,10 @@
+use std::ops::Range;
 
 use crate::string_builder;
 
+///char positions inside a line
+pub type Span = Range<usize>;
+
 #[repr(u8)]
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum ParenthesisSide {
@@ -11,13 +15,16 @@ pub enum ParenthesisSide {
 pub enum Token {
     Name(String),
     ArrayBracket(ParenthesisSide),
-    Literal(i32),
+    Literal(i64),
+    ///"quoted text" with escapes already replaced
+    Text(String),
     Comment(String),
 }
 
 #[derive(derive_new::new, Default, Debug)]
 pub struct TokenLine {
     pub tokens: Vec<Token>,
+    pub spans: Vec<Span>,
     pub line: String,
 }
 
@@ -66,19 +66,5 @@ where
         return self.position(|e| e == element);
     }
 }
-trait FlagExtension<T> {
-    fn use_if(self, b: bool) -> T;
-}
-impl<T> FlagExtension<T> for T
-where
-    T: bitflags::Flags,
-{
-    fn use_if(self, b: bool) -> Self {
-        if b {
-            return self;
-        }
-        return Self::empty();
-    }
-}
 
 
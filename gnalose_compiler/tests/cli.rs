@@ -0,0 +1,166 @@
+//exit codes and output streams of the command line tool
+
+use std::{
+    env, fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use gnalose_compiler::ExitCode;
+
+fn write_source(name: &str, code: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("gnalose_cli_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{name}.gnalose"));
+    fs::write(&path, code).unwrap();
+    return path;
+}
+
+fn run_cli(args: &[&str]) -> (i32, String, String) {
+    return run_cli_with_stdin(args, "");
+}
+
+fn run_cli_with_stdin(args: &[&str], input: &str) -> (i32, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gnalose_compiler"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    //cli may exit before reading anything
+    let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+    let output = child.wait_with_output().unwrap();
+    return (
+        output.status.code().unwrap(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    );
+}
+
+#[test]
+fn exit_codes() {
+    let cases = [
+        ("lex", "read to 99999999999", ExitCode::Lex),
+        ("parse", "read to a", ExitCode::Parse),
+        ("compile", "fi", ExitCode::Compile),
+    ];
+    for (name, code, expected) in cases {
+        let source = write_source(name, code);
+        let output = source.with_extension("c");
+        let (exit_code, stdout, stderr) = run_cli(&[source.to_str().unwrap(), "-o", output.to_str().unwrap()]);
+        assert_eq!(exit_code, expected.code(), "{name}");
+        assert!(stdout.is_empty(), "{name}: diagnostics should go to stderr");
+        assert!(stderr.contains("error["), "{name}");
+    }
+
+    assert_eq!(run_cli(&[]).0, ExitCode::Usage.code());
+    assert_eq!(run_cli(&["missing_file.gnalose"]).0, ExitCode::Io.code());
+
+    let source = write_source("ok", "define a\nundefine a");
+    let output = source.with_extension("c");
+    assert_eq!(run_cli(&[source.to_str().unwrap(), "-o", output.to_str().unwrap()]).0, 0);
+    let unwritable = source.join("output.c");
+    assert_eq!(
+        run_cli(&[source.to_str().unwrap(), "-o", unwritable.to_str().unwrap()]).0,
+        ExitCode::Io.code()
+    );
+
+    let source = write_source("leak", "undefine a");
+    let (exit_code, stdout, _) = run_cli(&["run", source.to_str().unwrap()]);
+    assert_eq!(exit_code, ExitCode::Leak.code());
+    assert!(stdout.starts_with("ABORTED\nMemory leaked: a."));
+
+    //the same warnings as compile, with --deny-leaks it doesn't run at all
+    let (_, _, stderr) = run_cli(&["run", source.to_str().unwrap()]);
+    assert!(stderr.contains("warning[G0008]"));
+    let (exit_code, stdout, stderr) = run_cli(&["run", source.to_str().unwrap(), "--deny-leaks"]);
+    assert_eq!(exit_code, ExitCode::Compile.code());
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("error[G0008]"));
+}
+
+#[test]
+fn subcommands_and_piping() {
+    assert_eq!(run_cli(&["--help"]).0, 0);
+    assert_eq!(run_cli(&["--version"]).0, 0);
+    assert_eq!(run_cli(&["compile", "a.gnalose", "--bogus"]).0, ExitCode::Usage.code());
+
+    //c goes to stdout, warnings and verbose output stay on stderr
+    let (exit_code, stdout, stderr) = run_cli_with_stdin(&["compile", "-", "-o", "-", "-v", "-p"], "undefine a");
+    assert_eq!(exit_code, 0);
+    assert!(stdout.starts_with("#include"));
+    assert!(stderr.contains("warning[G0008]") && stderr.contains("<stdin>:1"));
+    assert!(stderr.contains("TOKENIZATION DONE"));
+
+    let (exit_code, stdout, stderr) = run_cli_with_stdin(&["check", "-", "--deny-leaks"], "undefine a");
+    assert_eq!(exit_code, ExitCode::Compile.code());
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("error[G0008]"));
+
+    let source = write_source("run", "define a\nread to a\nprint a\nundefine a");
+    let (exit_code, stdout, _) = run_cli_with_stdin(&["run", source.to_str().unwrap()], "42");
+    assert_eq!((exit_code, stdout.as_str()), (0, "42\n"));
+}
+
+#[test]
+fn emit_executable() {
+    let source = write_source("exe", "define a\nread to a\nprint a\nundefine a");
+    let exe = source.with_extension("exe");
+    let (exit_code, _, stderr) = run_cli(&[
+        "compile",
+        source.to_str().unwrap(),
+        "--emit",
+        "exe",
+        "--cc",
+        "false",
+        "-o",
+        exe.to_str().unwrap(),
+    ]);
+    assert_eq!(exit_code, ExitCode::NativeBuild.code(), "{stderr}");
+
+    let cc = env::var("CC").ok().filter(|e| !e.is_empty()).unwrap_or("cc".to_owned());
+    //arguments after the compiler name are passed to it
+    let (exit_code, _, stderr) = run_cli(&[
+        "compile",
+        source.to_str().unwrap(),
+        "--emit",
+        "exe",
+        "--cc",
+        &format!("{cc} -w"),
+        "-O1",
+        "--on-bad-input",
+        "zero",
+        "-o",
+        exe.to_str().unwrap(),
+    ]);
+    if exit_code == ExitCode::NativeBuild.code() && stderr.contains("couldn't be started") {
+        eprintln!("no c compiler found, skipping");
+        return;
+    }
+    assert_eq!(exit_code, 0, "{stderr}");
+    let output = Command::new(&exe).stdin(Stdio::null()).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "0\n");
+}
+
+#[test]
+fn format_command() {
+    let (exit_code, stdout, _) = run_cli_with_stdin(&["fmt", "-"], "if 1 equal to  1\n read as number to 72\nfi");
+    assert_eq!(
+        (exit_code, stdout.as_str()),
+        (0, "if 1 equal to 1\n\tread as number to 72\nfi\n")
+    );
+
+    let source = write_source("fmt", "define a\n  undefine a");
+    let path = source.to_str().unwrap();
+    let (exit_code, _, stderr) = run_cli(&["fmt", "--check", path]);
+    assert_eq!(exit_code, ExitCode::Unformatted.code());
+    assert!(stderr.contains("fmt.gnalose:2: not formatted"), "{stderr}");
+    assert_eq!(fs::read_to_string(&source).unwrap(), "define a\n  undefine a");
+
+    assert_eq!(run_cli(&["fmt", path]).0, 0);
+    assert_eq!(fs::read_to_string(&source).unwrap(), "define a\nundefine a\n");
+    assert_eq!(run_cli(&["fmt", "--check", path]).0, 0);
+    assert_eq!(run_cli_with_stdin(&["fmt", "-"], "read to a").0, ExitCode::Parse.code());
+}
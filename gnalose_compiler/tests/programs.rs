@@ -0,0 +1,295 @@
+//runs every program from examples/ and tests/fixtures/ both compiled with system c compiler and interpreted
+//NAME.in is given as stdin, NAME.out is expected stdout and NAME.exit expected exit code (0 when missing)
+//NAME.args holds cli options the program is compiled and run with, e.g. "--int-width 8 --overflow trap"
+//programs without NAME.out are only compiled, since they may never finish
+
+use std::{
+    env, fs,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+use gnalose_compiler::{
+    compile_str,
+    integer::{IntWidth, Integers, Overflow},
+    interpreter, parse_str, BadInput, OptionFlags, Options,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Case {
+    source: PathBuf,
+    input: String,
+    ///compared as bytes, "read as number to" writes any byte
+    output: Option<Vec<u8>>,
+    exit_code: i32,
+    options: Options,
+}
+
+impl Case {
+    fn name(&self) -> String {
+        return self.source.file_stem().unwrap().to_string_lossy().into_owned();
+    }
+}
+
+fn read_sibling(source: &Path, extension: &str) -> Option<String> {
+    return fs::read_to_string(source.with_extension(extension)).ok();
+}
+
+//only options that change what the program does
+fn parse_args(args: &str) -> Options {
+    let mut options = Options::default();
+    let mut args = args.split_whitespace();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| panic!("{arg} without value"));
+        match arg {
+            "--int-width" => options.integers.width = IntWidth::from_str(value(), false).unwrap(),
+            "--overflow" => options.integers.overflow = Overflow::from_str(value(), false).unwrap(),
+            "--on-bad-input" => options.on_bad_input = BadInput::from_str(value(), false).unwrap(),
+            "--utf8-output" => options.flags |= OptionFlags::Utf8Output,
+            _ => panic!("unknown option {arg}"),
+        }
+    }
+    return options;
+}
+
+fn collect_cases() -> Vec<Case> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let dirs = [root.join("../examples"), root.join("tests/fixtures")];
+
+    let mut cases = Vec::new();
+    for dir in dirs {
+        let mut sources: Vec<PathBuf> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|e| e.extension().is_some_and(|ext| ext == "gnalose"))
+            .collect();
+        sources.sort();
+
+        for source in sources {
+            let exit_code = read_sibling(&source, "exit").map(|e| e.trim().parse().unwrap()).unwrap_or(0);
+            cases.push(Case {
+                input: read_sibling(&source, "in").unwrap_or_default(),
+                output: fs::read(source.with_extension("out")).ok(),
+                exit_code,
+                options: parse_args(&read_sibling(&source, "args").unwrap_or_default()),
+                source,
+            });
+        }
+    }
+    assert!(!cases.is_empty());
+    return cases;
+}
+
+fn find_c_compiler() -> Option<String> {
+    let cc = env::var("CC").unwrap_or("cc".to_owned());
+    let works = Command::new(&cc)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|e| e.success());
+    return if works { Some(cc) } else { None };
+}
+
+fn run_executable(exe: &Path, input: &str) -> (Vec<u8>, i32) {
+    let mut child = Command::new(exe)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    //program may exit before reading everything
+    let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+
+    let mut stdout = child.stdout.take().unwrap();
+    let reader = thread::spawn(move || {
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output).unwrap();
+        output
+    });
+
+    let deadline = Instant::now() + TIMEOUT;
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("{} didn't finish in {:?}", exe.display(), TIMEOUT);
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let output = reader.join().unwrap();
+    return (output, status.code().unwrap_or(-1));
+}
+
+//shown as text, bytes that aren't utf-8 are escaped
+fn assert_output(output: &[u8], expected: &[u8], name: &str) {
+    assert_eq!(
+        output.escape_ascii().to_string(),
+        expected.escape_ascii().to_string(),
+        "{name}: wrong output"
+    );
+}
+
+//panics when c code doesn't compile
+fn build(cc: &str, c_code: &str, cc_args: &[&str], exe_path: &Path) -> PathBuf {
+    let c_path = exe_path.with_extension("c");
+    fs::write(&c_path, c_code).unwrap();
+    let build = Command::new(cc)
+        .args(cc_args)
+        .arg(&c_path)
+        .arg("-o")
+        .arg(exe_path)
+        .output()
+        .unwrap();
+    assert!(
+        build.status.success(),
+        "{}: generated c doesn't compile\n{}",
+        exe_path.display(),
+        String::from_utf8_lossy(&build.stderr)
+    );
+    return exe_path.to_owned();
+}
+
+#[test]
+fn compiled_programs() {
+    let Some(cc) = find_c_compiler() else {
+        eprintln!("no c compiler found, skipping compiled programs");
+        return;
+    };
+    let dir = env::temp_dir().join(format!("gnalose_programs_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    //portable output (with #line directives) has to be accepted by strict standard c compiler and behave the same
+    let modes = [
+        ("", OptionFlags::empty(), vec![]),
+        (
+            "_portable",
+            OptionFlags::Portable | OptionFlags::LineDirectives,
+            vec!["-std=c99", "-pedantic-errors"],
+        ),
+        ("_optimized", OptionFlags::Optimize, vec![]),
+        ("_trap", OptionFlags::Optimize, vec!["-O2"]),
+    ];
+    for case in collect_cases() {
+        for (suffix, flags, cc_args) in modes.iter() {
+            let name = format!("{}{suffix}", case.name());
+            let mut options = case.options.clone();
+            options.flags |= *flags;
+            //none of the programs overflows 64-bit integer, so checked math can't change anything
+            //programs with their own integer options keep them
+            if *suffix == "_trap" && options.integers == Integers::default() {
+                options.integers = Integers::new(IntWidth::W64, Overflow::Trap);
+            }
+            let code = fs::read_to_string(&case.source).unwrap();
+            let c_code = compile_str(&code, options).unwrap_or_else(|err| panic!("{name}:{err}"));
+
+            let exe_path = build(&cc, &c_code, cc_args, &dir.join(&name));
+
+            if let Some(expected) = &case.output {
+                let (output, exit_code) = run_executable(&exe_path, &case.input);
+                assert_output(&output, expected, &name);
+                assert_eq!(exit_code, case.exit_code, "{name}: wrong exit code");
+            }
+        }
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn interpreted_programs() {
+    for case in collect_cases() {
+        let name = case.name();
+        let Some(expected) = &case.output else {
+            continue;
+        };
+        let code = fs::read_to_string(&case.source).unwrap();
+        let repr = parse_str(&code, case.options.clone()).unwrap_or_else(|err| panic!("{name}:{err}"));
+
+        let mut output = Vec::new();
+        let result = interpreter::run_with_args(&repr, &case.options, &mut case.input.as_bytes(), &mut output);
+        assert_output(&output, expected, &name);
+        let exit_code = match &result {
+            Ok(()) => 0,
+            Err(interpreter::RunError::Runtime(err)) => err.exit_code().code(),
+            Err(err) => panic!("{name}: {err}"),
+        };
+        assert_eq!(exit_code, case.exit_code, "{name}: wrong exit code");
+    }
+}
+
+//amount of output bytes the program writes in given time, for programs that never finish
+fn measure_throughput(exe: &Path, time: Duration) -> usize {
+    let mut child = Command::new(exe).stdin(Stdio::null()).stdout(Stdio::piped()).spawn().unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    let reader = thread::spawn(move || {
+        let mut buffer = [0; 1 << 16];
+        let mut total = 0;
+        while let Ok(read) = stdout.read(&mut buffer) {
+            if read == 0 {
+                break;
+            }
+            total += read;
+        }
+        total
+    });
+    thread::sleep(time);
+    child.kill().unwrap();
+    child.wait().unwrap();
+    return reader.join().unwrap();
+}
+
+//cargo test --release -- --ignored --nocapture
+//c is compiled without optimizations, so the difference comes from the generated code
+#[test]
+#[ignore]
+fn optimizer_speed() {
+    let Some(cc) = find_c_compiler() else {
+        eprintln!("no c compiler found, skipping");
+        return;
+    };
+    let dir = env::temp_dir().join(format!("gnalose_speed_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples");
+    let build_both = |name: &str| {
+        let code = fs::read_to_string(root.join(format!("{name}.gnalose"))).unwrap();
+        return [Options::default(), Options::new(OptionFlags::Optimize)].map(|options| {
+            let suffix = if options.flags.contains(OptionFlags::Optimize) {
+                "_optimized"
+            } else {
+                ""
+            };
+            let c_code = compile_str(&code, options).unwrap();
+            return build(&cc, &c_code, &["-O0"], &dir.join(format!("{name}{suffix}")));
+        });
+    };
+
+    //1to100 finishes quickly, so its time is mostly starting the process, its only math has to be folded
+    let code = fs::read_to_string(root.join("1to100.gnalose")).unwrap();
+    assert!(!compile_str(&code, Options::new(OptionFlags::Optimize))
+        .unwrap()
+        .contains("value temp"));
+    let [plain, optimized] = build_both("1to100");
+    let time_runs = |exe: &Path| {
+        let start = Instant::now();
+        for _ in 0..1000 {
+            Command::new(exe).stdin(Stdio::null()).output().unwrap();
+        }
+        return start.elapsed();
+    };
+    let (plain_time, optimized_time) = (time_runs(&plain), time_runs(&optimized));
+    eprintln!("1to100: {plain_time:?} -> {optimized_time:?} (optimized)");
+
+    let [plain, optimized] = build_both("fib");
+    let time = Duration::from_secs(2);
+    let (plain_bytes, optimized_bytes) = (measure_throughput(&plain, time), measure_throughput(&optimized, time));
+    eprintln!("fib: {plain_bytes} -> {optimized_bytes} (optimized) bytes in {time:?}");
+
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(optimized_bytes >= plain_bytes, "fib got slower");
+}